@@ -6,14 +6,14 @@ async fn main() -> anyhow::Result<()> {
     use std::io;
 
     use extrahop::activitymap::{query, Edge, Query, Response, Source, Walk};
-    use extrahop::{ApiResponse, Client};
+    use extrahop::{ApiResponse, Client, Oid};
 
     fn write_edge(f: &mut dyn io::Write, edge: &Edge) -> io::Result<()> {
         writeln!(
             f,
             "{from}\t{to}\t{weight}",
-            from = edge.from.as_url_part(),
-            to = edge.to.as_url_part(),
+            from = edge.from,
+            to = edge.to,
             weight = edge.weight
         )
     }
@@ -25,9 +25,8 @@ async fn main() -> anyhow::Result<()> {
     let request = Query::builder()
         .from("-1w")
         .walks(vec![Walk {
-            origins: vec![Source::device_group(1)].into(),
+            origins: vec![Source::device_group(Oid::new(1))].into(),
             steps: vec![Default::default()],
-            ..Default::default()
         }])
         .edge_annotations(vec![query::EdgeAnnotation::Protocols])
         .build()?;
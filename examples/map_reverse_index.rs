@@ -56,8 +56,8 @@ impl From<Response> for IndexedTopology {
             };
 
             for (index, edge) in map.iter().enumerate() {
-                add_to_index(index, edge.from.clone());
-                add_to_index(index, edge.to.clone());
+                add_to_index(index, edge.from);
+                add_to_index(index, edge.to);
             }
         }
 
@@ -82,7 +82,7 @@ async fn main() -> anyhow::Result<()> {
     // However, the client can be reused to make many requests.
     let client = Client::new_appliance("your-host", "YOUR-KEY".into(), Default::default()).await?;
 
-    let query = Query::builder().from(-30000).build()?;
+    let query = Query::builder().from(-30000i64).build()?;
 
     let rsp: IndexedTopology = client
         .post("v1/activitymaps/query")?
@@ -98,7 +98,7 @@ async fn main() -> anyhow::Result<()> {
 
     let nodes = rsp.nodes();
     for node in nodes.into_iter().take(1) {
-        for edge in rsp.get(node.clone()) {
+        for edge in rsp.get(*node) {
             println!("{:?}", edge);
         }
     }
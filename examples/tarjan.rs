@@ -10,7 +10,7 @@ async fn main() -> anyhow::Result<()> {
     let client = Client::new_appliance("YOUR-HOST", "YOUR-KEY".into(), Default::default()).await?;
 
     let query = Query::builder()
-        .from(-30000)
+        .from(-30000i64)
         .walks(vec![Default::default()])
         .build()?;
 
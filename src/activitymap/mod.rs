@@ -9,14 +9,14 @@
 //!
 //! ## Using Builders
 //! ```rust
-//! use extrahop::Oid;
 //! use extrahop::activitymap::{self, Walk, Source, Step};
+//! use extrahop::Oid;
 //!
 //! // Create a request for the last half hour, starting from device 15 and
 //! // finding all its immediate peers. The default weight strategy will be
 //! // used, and no extra annotations were requested.
 //! let _ = activitymap::Query::builder()
-//!             .from(-30000)
+//!             .from(-30000i64)
 //!             .walks(vec![
 //!                 Walk::builder()
 //!                     .origins(vec![Source::device(Oid::new(15))])
@@ -25,15 +25,52 @@
 //!             ])
 //!             .build().unwrap();
 //! ```
+//!
+//! ## Text Syntax
+//! Queries can also be parsed from and printed as a compact text syntax, which is convenient
+//! for command-line tools.
+//!
+//! A query is a list of statements separated by `;`. Each statement is either a walk, or
+//! `from <time>` or `until <time>` to set the query's window using any syntax accepted by
+//! [`QueryTime`](crate::QueryTime)'s `FromStr` impl.
+//!
+//! A walk starts with its origins, either `all`, `none`, or a comma-separated list of sources
+//! such as `device:15` or `group:1`, followed by each step introduced with `->`. A step lists
+//! the relationships to follow as `protocol:role`, where either part may be `any` and the role
+//! may be left out, then any number of `[in <sources>]` and `[not in <sources>]` peer
+//! filters. A bare `any` leaves the step's relationships unrestricted.
+//!
+//! Protocol names which contain other characters or are keywords, such as `"SSL/TLS"` or
+//! `"any"`, are written in double quotes, with `\"` and `\\` escaping quotes and backslashes.
+//! An empty query parses as the default query.
+//!
+//! ```rust
+//! use extrahop::activitymap::Query;
+//!
+//! let query: Query = r#"from -30m; group:1 -> http:server -> "SSL/TLS" [not in group:7]"#
+//!     .parse()
+//!     .unwrap();
+//! assert_eq!(2, query.walks[0].steps.len());
+//! assert_eq!(
+//!     r#"from -30m; group:1 -> http:server -> "SSL/TLS" [not in group:7]"#,
+//!     query.to_string()
+//! );
+//! ```
+//!
+//! The query's weighting and edge annotations are not part of the syntax, so they keep
+//! their default values when parsing and are left out when printing.
 
 pub mod query;
 pub mod rsp;
+mod text;
 
 #[doc(inline)]
-pub use self::query::{Query, Source, Step, Walk, WalkOrigin};
+pub use self::query::{InvalidQuery, Problem, Query, Source, Step, Walk, WalkOrigin};
+
+pub use self::text::ParseQueryError;
 
 #[doc(inline)]
-pub use self::rsp::{Edge, Response};
+pub use self::rsp::{Edge, Response, WeightMerge};
 
 #[cfg(test)]
 mod tests {
@@ -46,7 +83,7 @@ mod tests {
     #[test]
     fn it_works() {
         let request = Query {
-            from: 0.into(),
+            from: 0i64.into(),
             walks: vec![Walk {
                 origins: vec![Source::device(Oid::new(14))].into(),
                 steps: vec![Step {
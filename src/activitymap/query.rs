@@ -7,15 +7,27 @@
 //! readability.
 
 use crate::activitymap::rsp::Appearance;
-use crate::{Oid, QueryTime};
+use crate::oid::{Device, DeviceGroup};
+use crate::time_slices::Windowed;
+use crate::{Oid, ParseQueryTimeError, QueryTime};
 use derive_builder::Builder;
 use serde::ser::SerializeSeq;
-use serde::{Deserialize, Serialize, Serializer};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
 use std::ops::Index;
+use thiserror::Error;
+
+/// The largest number of walks the appliance accepts in a single query.
+pub const MAX_WALKS: usize = 4;
+
+/// The largest number of steps the appliance accepts in a single walk.
+pub const MAX_STEPS: usize = 5;
 
 /// Envelope for an ad-hoc activity map query.
+///
+/// Queries created with [`Query::builder`] are checked with [`Query::validate`] when built.
 #[derive(Debug, Clone, Serialize, Deserialize, Default, Builder)]
-#[builder(default, setter(into))]
+#[builder(default, setter(into), build_fn(private, name = "build_unvalidated"))]
 #[serde(default)]
 #[non_exhaustive]
 pub struct Query {
@@ -42,6 +54,137 @@ impl Query {
     pub fn builder() -> QueryBuilder {
         QueryBuilder::default()
     }
+
+    /// Check the query for problems which would cause the appliance to reject it or
+    /// return an empty map. An empty list means no problems were found.
+    ///
+    /// The time window can only be checked when `from` and `until` are both absolute or
+    /// both relative, since otherwise it depends on the appliance's current time.
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut problems = vec![];
+
+        // Any reference time works when both ends are relative, and none is needed when both
+        // are absolute.
+        match (self.from.resolve(u64::MAX), self.until.resolve(u64::MAX)) {
+            (Ok(from), Ok(until)) => {
+                if from > until && self.from.is_relative() == self.until.is_relative() {
+                    problems.push(Problem::FromAfterUntil);
+                }
+            }
+            (Err(e), _) | (_, Err(e)) => problems.push(Problem::InvalidTime(e)),
+        }
+
+        if self.walks.len() > MAX_WALKS {
+            problems.push(Problem::TooManyWalks {
+                count: self.walks.len(),
+            });
+        }
+
+        for (walk_idx, walk) in self.walks.iter().enumerate() {
+            let walk_idx = walk_idx as u16;
+
+            if walk.origins == WalkOrigin::Specific(vec![]) {
+                problems.push(Problem::NoOrigins { walk: walk_idx });
+            }
+
+            if walk.steps.is_empty() {
+                problems.push(Problem::NoSteps { walk: walk_idx });
+            } else if walk.steps.len() > MAX_STEPS {
+                problems.push(Problem::TooManySteps {
+                    walk: walk_idx,
+                    count: walk.steps.len(),
+                });
+            }
+
+            for (step_idx, step) in walk.steps.iter().enumerate() {
+                for (i, peer) in step.peer_in.iter().enumerate() {
+                    // Report each conflicting peer once, however many times it is listed.
+                    if step.peer_in[..i].contains(peer) || !step.peer_not_in.contains(peer) {
+                        continue;
+                    }
+
+                    problems.push(Problem::PeerInAndNotIn {
+                        step: Appearance::new(walk_idx, step_idx as u16),
+                        peer: peer.clone(),
+                    });
+                }
+            }
+        }
+
+        problems
+    }
+}
+
+impl QueryBuilder {
+    /// Build the query, checking it with [`Query::validate`].
+    pub fn build(&self) -> Result<Query, InvalidQuery> {
+        let query = self
+            .build_unvalidated()
+            .expect("Every query field has a default");
+
+        let problems = query.validate();
+        if problems.is_empty() {
+            Ok(query)
+        } else {
+            Err(InvalidQuery { problems })
+        }
+    }
+}
+
+/// A problem found by [`Query::validate`].
+///
+/// Walk and step indices match those in [`Appearance`], so a problem can be traced back to
+/// the same part of the query as an edge in the response.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum Problem {
+    #[error("Query starts after it ends")]
+    FromAfterUntil,
+    #[error("Query time is not valid: {0}")]
+    InvalidTime(ParseQueryTimeError),
+    #[error("Query has {count} walks; at most {} are allowed", MAX_WALKS)]
+    TooManyWalks { count: usize },
+    #[error("Walk {walk} has no origins")]
+    NoOrigins { walk: u16 },
+    #[error("Walk {walk} has no steps")]
+    NoSteps { walk: u16 },
+    #[error("Walk {walk} has {count} steps; at most {} are allowed", MAX_STEPS)]
+    TooManySteps { walk: u16, count: usize },
+    #[error("Step {} of walk {} both includes and excludes {peer}", step.step, step.walk)]
+    PeerInAndNotIn { step: Appearance, peer: Source },
+}
+
+/// The error returned when building a query which fails [`Query::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidQuery {
+    pub problems: Vec<Problem>,
+}
+
+impl fmt::Display for InvalidQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid activity map query")?;
+        for (i, problem) in self.problems.iter().enumerate() {
+            write!(f, "{} {}", if i == 0 { ":" } else { ";" }, problem)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for InvalidQuery {}
+
+impl Windowed for Query {
+    fn window(&self) -> (QueryTime, QueryTime) {
+        (self.from.clone(), self.until.clone())
+    }
+
+    fn with_window(&self, from: QueryTime, until: QueryTime) -> Self {
+        Query {
+            from,
+            until,
+            ..self.clone()
+        }
+    }
 }
 
 /// Find a step configuration for an `rsp::Appearance` from the edge list in
@@ -64,11 +207,12 @@ impl From<Walk> for Query {
 }
 
 /// The type of metrics that should be used to compute edge weight.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Weighting {
     /// The number of bytes transferred in both directions between the two peers.
     /// This is the default strategy.
+    #[default]
     Bytes,
 
     /// The number of connections *established* during the time interval.
@@ -80,12 +224,6 @@ pub enum Weighting {
     Turns,
 }
 
-impl Default for Weighting {
-    fn default() -> Self {
-        Weighting::Bytes
-    }
-}
-
 /// Flags to opt into additional data about the topology from the appliance.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -127,8 +265,7 @@ impl Default for Walk {
 }
 
 /// Sets the origins for a walk.
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
-#[serde(untagged)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum WalkOrigin {
     /// Starts the walk at every compatible device.
     All,
@@ -147,7 +284,7 @@ impl Serialize for WalkOrigin {
                 #[derive(Serialize)]
                 struct AllDevices {
                     object_type: &'static str,
-                };
+                }
 
                 (vec![AllDevices {
                     object_type: "all_devices",
@@ -166,6 +303,58 @@ impl Serialize for WalkOrigin {
     }
 }
 
+impl<'de> Deserialize<'de> for WalkOrigin {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        /// An entry in the origins list, which may be the "all_devices" marker written by
+        /// the `Serialize` impl rather than a real source.
+        #[derive(Deserialize)]
+        struct Origin {
+            object_type: OriginType,
+            #[serde(default)]
+            object_id: Option<Oid>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum OriginType {
+            AllDevices(AllDevices),
+            Object(ObjectType),
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "snake_case")]
+        enum AllDevices {
+            AllDevices,
+        }
+
+        // Earlier versions accepted `null` for `All`, so keep doing so.
+        let origins = match Option::<Vec<Origin>>::deserialize(d)? {
+            Some(origins) => origins,
+            None => return Ok(WalkOrigin::All),
+        };
+
+        let mut all_devices = false;
+        let mut sources = Vec::with_capacity(origins.len());
+        for origin in origins {
+            match (origin.object_type, origin.object_id) {
+                (OriginType::AllDevices(_), _) => all_devices = true,
+                (OriginType::Object(object_type), Some(id)) => {
+                    sources.push(Source::new(object_type, id))
+                }
+                (OriginType::Object(_), None) => return Err(de::Error::missing_field("object_id")),
+            }
+        }
+
+        match (all_devices, sources.is_empty()) {
+            (false, _) => Ok(WalkOrigin::Specific(sources)),
+            (true, true) => Ok(WalkOrigin::All),
+            (true, false) => Err(de::Error::custom(
+                "`all_devices` cannot be combined with other walk origins",
+            )),
+        }
+    }
+}
+
 impl From<Vec<Source>> for WalkOrigin {
     fn from(val: Vec<Source>) -> Self {
         WalkOrigin::Specific(val)
@@ -189,13 +378,13 @@ impl Source {
     }
 
     /// Create a new `Source` instance for a device.
-    pub fn device(id: impl Into<Oid>) -> Self {
-        Source::new(ObjectType::Device, id.into())
+    pub fn device(id: impl Into<Oid<Device>>) -> Self {
+        Source::new(ObjectType::Device, id.into().untyped())
     }
 
     /// Create a new `Source` instance for a device group.
-    pub fn device_group(id: impl Into<Oid>) -> Self {
-        Source::new(ObjectType::DeviceGroup, id.into())
+    pub fn device_group(id: impl Into<Oid<DeviceGroup>>) -> Self {
+        Source::new(ObjectType::DeviceGroup, id.into().untyped())
     }
 }
 
@@ -223,7 +412,7 @@ pub enum ObjectType {
 /// * If `relationships` is set to a single protocol and role pair, such as "http server",
 ///   it is not necessary to also apply a `peer_in` filter for the HTTP Servers activity
 ///   group.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Builder)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Builder)]
 #[serde(default)]
 #[builder(default, setter(into))]
 pub struct Step {
@@ -251,16 +440,6 @@ impl Step {
     }
 }
 
-impl Default for Step {
-    fn default() -> Self {
-        Step {
-            relationships: vec![],
-            peer_in: vec![],
-            peer_not_in: vec![],
-        }
-    }
-}
-
 /// A combination of protocol and peer role which can match a connection between devices.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(default)]
@@ -302,11 +481,12 @@ impl From<Role> for Relationship {
 }
 
 /// The role an endpoint is able to fill in a network transaction.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Role {
     Client,
     Server,
+    #[default]
     Any,
 }
 
@@ -318,19 +498,13 @@ impl Role {
     }
 }
 
-impl Default for Role {
-    fn default() -> Self {
-        Role::Any
-    }
-}
-
 /// A protocol name that will be used to filter the edges traversed during the walk.
 ///
 /// Unlike `rsp::ProtocolStack`, this is a single string and not a full stack.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Protocol(String);
 
-impl<'a> From<&'a str> for Protocol {
+impl From<&str> for Protocol {
     fn from(val: &str) -> Self {
         Protocol(String::from(val))
     }
@@ -342,9 +516,107 @@ impl From<String> for Protocol {
     }
 }
 
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Strategies for generating queries in property tests.
+#[cfg(test)]
+pub(crate) mod strategies {
+    use super::{
+        EdgeAnnotation, ObjectType, Protocol, Query, Relationship, Role, Source, Step, Walk,
+        WalkOrigin, Weighting,
+    };
+    use crate::QueryTime;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+    use proptest::sample::select;
+
+    pub(crate) fn source() -> impl Strategy<Value = Source> {
+        let object_type = select(vec![
+            ObjectType::Device,
+            ObjectType::DeviceGroup,
+            ObjectType::ActivityGroup,
+        ]);
+        (object_type, any::<u64>()).prop_map(|(object_type, id)| Source::new(object_type, id))
+    }
+
+    pub(crate) fn relationship() -> impl Strategy<Value = Relationship> {
+        let role = select(vec![Role::Client, Role::Server, Role::Any]);
+        // Include keywords and arbitrary text, which the text syntax has to quote.
+        let name = prop_oneof![
+            "[A-Za-z0-9_-]{1,12}",
+            select(vec!["any", "all", "none", "from", "until", "not", "in"]).prop_map(String::from),
+            any::<String>(),
+        ];
+        let protocol = proptest::option::of(name.prop_map(Protocol::from));
+        (role, protocol).prop_map(|(role, protocol)| Relationship { role, protocol })
+    }
+
+    pub(crate) fn step() -> impl Strategy<Value = Step> {
+        (
+            vec(relationship(), 0..3),
+            vec(source(), 0..3),
+            vec(source(), 0..3),
+        )
+            .prop_map(|(relationships, peer_in, peer_not_in)| Step {
+                relationships,
+                peer_in,
+                peer_not_in,
+            })
+    }
+
+    pub(crate) fn walk() -> impl Strategy<Value = Walk> {
+        let origins = prop_oneof![
+            Just(WalkOrigin::All),
+            vec(source(), 0..4).prop_map(WalkOrigin::Specific),
+        ];
+        (origins, vec(step(), 0..6)).prop_map(|(origins, steps)| Walk { origins, steps })
+    }
+
+    pub(crate) fn query_time() -> impl Strategy<Value = QueryTime> {
+        let units = select(vec!["ms", "s", "m", "h", "d", "w", "y"]);
+        prop_oneof![
+            Just(QueryTime::default()),
+            (1..u64::MAX).prop_map(QueryTime::from),
+            (1..i64::MAX).prop_map(|ms| QueryTime::from(-ms)),
+            (1..1000u32, units).prop_map(|(n, unit)| QueryTime::from(format!("-{}{}", n, unit))),
+        ]
+    }
+
+    pub(crate) fn query() -> impl Strategy<Value = Query> {
+        let weighting = select(vec![
+            Weighting::Bytes,
+            Weighting::Connections,
+            Weighting::Turns,
+        ]);
+        let annotation = select(vec![EdgeAnnotation::Appearances, EdgeAnnotation::Protocols]);
+        (
+            query_time(),
+            query_time(),
+            vec(walk(), 0..5),
+            weighting,
+            vec(annotation, 0..3),
+        )
+            .prop_map(|(from, until, walks, weighting, edge_annotations)| Query {
+                from,
+                until,
+                walks,
+                weighting,
+                edge_annotations,
+            })
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::WalkOrigin;
+    use super::{strategies, Problem, Query, Source, Step, Walk, WalkOrigin};
+    use crate::activitymap::rsp::Appearance;
+    use crate::Oid;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
 
     #[test]
     fn source_list_serialize_all_devices() {
@@ -353,4 +625,108 @@ mod tests {
             serde_json::to_string(&WalkOrigin::All).unwrap()
         );
     }
+
+    #[test]
+    fn source_list_deserialize_all_devices() {
+        let all: WalkOrigin = serde_json::from_str(r#"[{"object_type":"all_devices"}]"#).unwrap();
+        assert_eq!(WalkOrigin::All, all);
+
+        let specific: WalkOrigin =
+            serde_json::from_str(r#"[{"object_type":"device_group","object_id":"7"}]"#).unwrap();
+        assert_eq!(
+            WalkOrigin::Specific(vec![Source::device_group(Oid::new(7))]),
+            specific
+        );
+
+        assert!(serde_json::from_str::<WalkOrigin>(
+            r#"[{"object_type":"all_devices"},{"object_type":"device","object_id":1}]"#
+        )
+        .is_err());
+        assert!(serde_json::from_str::<WalkOrigin>(r#"[{"object_type":"device"}]"#).is_err());
+    }
+
+    #[test]
+    fn validate() {
+        let query = Query {
+            from: "-1h".into(),
+            until: "-2h".into(),
+            walks: vec![
+                Walk::default(),
+                Walk {
+                    origins: WalkOrigin::Specific(vec![]),
+                    steps: vec![],
+                },
+                Walk {
+                    origins: WalkOrigin::All,
+                    steps: vec![
+                        Step::default(),
+                        Step {
+                            peer_in: vec![
+                                Source::device_group(Oid::new(7)),
+                                Source::device_group(Oid::new(8)),
+                                Source::device_group(Oid::new(7)),
+                            ],
+                            peer_not_in: vec![Source::device_group(Oid::new(7))],
+                            ..Default::default()
+                        },
+                    ],
+                },
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            vec![
+                Problem::FromAfterUntil,
+                Problem::NoOrigins { walk: 1 },
+                Problem::NoSteps { walk: 1 },
+                Problem::PeerInAndNotIn {
+                    step: Appearance::new(2, 1),
+                    peer: Source::device_group(Oid::new(7)),
+                },
+            ],
+            query.validate()
+        );
+    }
+
+    #[test]
+    fn build_validates() {
+        assert!(Query::builder()
+            .walks(vec![Walk::default()])
+            .build()
+            .is_ok());
+
+        let error = Query::builder()
+            .walks(vec![Walk::default(); super::MAX_WALKS + 1])
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            "Invalid activity map query: Query has 5 walks; at most 4 are allowed",
+            error.to_string()
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn walk_origin_round_trip(origins in prop_oneof![
+            Just(WalkOrigin::All),
+            vec(strategies::source(), 0..4).prop_map(WalkOrigin::Specific),
+        ]) {
+            let json = serde_json::to_string(&origins).unwrap();
+            prop_assert_eq!(origins, serde_json::from_str::<WalkOrigin>(&json).unwrap());
+        }
+
+        #[test]
+        fn query_round_trip(query in strategies::query()) {
+            let json = serde_json::to_string(&query).unwrap();
+            let parsed: Query = serde_json::from_str(&json).unwrap();
+
+            prop_assert_eq!(query.from.to_string(), parsed.from.to_string());
+            prop_assert_eq!(query.until.to_string(), parsed.until.to_string());
+            prop_assert_eq!(&query.walks, &parsed.walks);
+            prop_assert_eq!(query.weighting, parsed.weighting);
+            prop_assert_eq!(&query.edge_annotations, &parsed.edge_annotations);
+            prop_assert_eq!(json, serde_json::to_string(&parsed).unwrap());
+        }
+    }
 }
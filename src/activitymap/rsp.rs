@@ -1,17 +1,17 @@
 //! Types for deserializing a response from `/api/v1/activitymaps/query`
 
-use crate::Oid;
+use crate::fleet::Merge;
+use crate::{Oid, QueryRange};
 #[cfg(feature = "petgraph")]
 use petgraph::Graph;
 use serde::{Deserialize, Serialize};
 use serde_json;
-#[cfg(feature = "petgraph")]
-use std::collections::HashMap;
-use std::collections::HashSet;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
 use std::{cmp, fmt, slice, vec};
 
 /// A successful response to a single topology API request.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Response {
     /// Non-fatal errors encountered during the construction of the map.
@@ -32,8 +32,8 @@ impl Response {
     pub fn nodes(&self) -> HashSet<Oid> {
         let mut oids = HashSet::new();
         for edge in &self.edges {
-            oids.insert(edge.from.clone());
-            oids.insert(edge.to.clone());
+            oids.insert(edge.from);
+            oids.insert(edge.to);
         }
 
         oids
@@ -49,15 +49,72 @@ impl Response {
     pub fn is_complete(&self) -> bool {
         self.warnings.is_empty()
     }
+
+    /// Gets the window of time covered by the response.
+    ///
+    /// The response's `until` is the appliance's current time when the query ended at "now",
+    /// so it can be used to resolve the relative times in other queries sent at the same time.
+    pub fn range(&self) -> QueryRange {
+        QueryRange::new(self.from, self.until)
+    }
+
+    /// Combine another response from the same appliance into this one, such as one for a
+    /// different time slice.
+    ///
+    /// The time range widens to cover both responses and warnings are concatenated. Edges
+    /// between the same pair of devices are combined into one using `weights`; see
+    /// [`Edge::merge_with`].
+    pub fn merge_with(&mut self, other: Response, weights: WeightMerge) {
+        self.from = cmp::min(self.from, other.from);
+        self.until = cmp::max(self.until, other.until);
+        self.warnings.extend(other.warnings);
+        self.edges.extend(other.edges);
+        self.dedup_edges(weights);
+    }
+
+    /// Combine edges between the same pair of devices, keeping the position of the first
+    /// edge for each pair.
+    pub fn dedup_edges(&mut self, weights: WeightMerge) {
+        let mut positions: HashMap<_, usize> = HashMap::with_capacity(self.edges.len());
+        let mut edges: Vec<Edge> = Vec::with_capacity(self.edges.len());
+        for edge in self.edges.drain(..) {
+            match positions.entry(edge.to_tuple()) {
+                Entry::Occupied(position) => edges[*position.get()].merge_with(edge, weights),
+                Entry::Vacant(position) => {
+                    position.insert(edges.len());
+                    edges.push(edge);
+                }
+            }
+        }
+
+        self.edges = edges;
+    }
 }
 
-impl Default for Response {
-    fn default() -> Self {
-        Response {
-            warnings: vec![],
-            from: 0,
-            until: 0,
-            edges: vec![],
+/// How to combine the weights of an edge or protocol which appears in more than one
+/// response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WeightMerge {
+    /// Add the weights together, saturating on overflow. Use this when the responses cover
+    /// different traffic, such as consecutive time slices.
+    Sum,
+    /// Keep the larger weight. Use this when the responses may have counted the same
+    /// traffic, such as overlapping windows.
+    Max,
+}
+
+impl WeightMerge {
+    fn edge_weight(self, a: usize, b: usize) -> usize {
+        match self {
+            WeightMerge::Sum => a.saturating_add(b),
+            WeightMerge::Max => cmp::max(a, b),
+        }
+    }
+
+    fn protocol_weight(self, a: u32, b: u32) -> u32 {
+        match self {
+            WeightMerge::Sum => a.saturating_add(b),
+            WeightMerge::Max => cmp::max(a, b),
         }
     }
 }
@@ -80,6 +137,21 @@ impl<'a> IntoIterator for &'a Response {
     }
 }
 
+/// Combine responses from several appliances by widening the time range to cover both
+/// responses and concatenating their edges and warnings.
+///
+/// Edges are not combined, since device IDs are local to each appliance and the same ID on
+/// two appliances may refer to different devices. Use [`Response::merge_with`] to combine
+/// responses from the same appliance.
+impl Merge for Response {
+    fn merge(&mut self, other: Self) {
+        self.from = cmp::min(self.from, other.from);
+        self.until = cmp::max(self.until, other.until);
+        self.warnings.extend(other.warnings);
+        self.edges.extend(other.edges);
+    }
+}
+
 #[cfg(feature = "petgraph")]
 impl From<Response> for Graph<Oid, Edge> {
     fn from(val: Response) -> Self {
@@ -87,7 +159,7 @@ impl From<Response> for Graph<Oid, Edge> {
         let nodes = val.nodes();
         let mut node_idx = HashMap::with_capacity(nodes.len());
         for node in nodes {
-            node_idx.insert(node.clone(), graph.add_node(node));
+            node_idx.insert(node, graph.add_node(node));
         }
 
         for edge in val.edges {
@@ -158,7 +230,17 @@ impl Edge {
     }
 
     pub fn to_tuple(&self) -> (Oid, Oid) {
-        (self.from.clone(), self.to.clone())
+        (self.from, self.to)
+    }
+
+    /// Combine another edge between the same devices into this one.
+    ///
+    /// Weights are combined using `weights`, protocol annotations with the same protocol
+    /// stack are combined the same way, and appearances are unioned. An annotation present
+    /// on either edge is present on the result.
+    pub fn merge_with(&mut self, other: Edge, weights: WeightMerge) {
+        self.weight = weights.edge_weight(self.weight, other.weight);
+        self.annotations.merge_with(other.annotations, weights);
     }
 }
 
@@ -176,8 +258,36 @@ pub struct EdgeAnnotations {
     pub protocols: Option<Vec<ProtocolAnnotation>>,
 }
 
+impl EdgeAnnotations {
+    fn merge_with(&mut self, other: EdgeAnnotations, weights: WeightMerge) {
+        if let Some(other) = other.appearances {
+            let appearances = self.appearances.get_or_insert_with(Vec::new);
+            appearances.extend(other);
+            appearances.sort();
+            appearances.dedup();
+        }
+
+        if let Some(other) = other.protocols {
+            let protocols = self.protocols.get_or_insert_with(Vec::new);
+            for annotation in other {
+                match protocols
+                    .iter_mut()
+                    .find(|existing| existing.protocol == annotation.protocol)
+                {
+                    Some(existing) => {
+                        existing.weight =
+                            weights.protocol_weight(existing.weight, annotation.weight)
+                    }
+                    None => protocols.push(annotation),
+                }
+            }
+        }
+    }
+}
+
 /// A walk index and step index into the request.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Ord, Hash, Serialize, Deserialize)]
+/// Appearances are ordered by walk, then by step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Appearance {
     /// The index of the walk that contributed to this appearance.
     pub walk: u16,
@@ -192,17 +302,6 @@ impl Appearance {
     }
 }
 
-impl PartialOrd for Appearance {
-    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
-        let walk = self.walk.cmp(&other.walk);
-        if walk == cmp::Ordering::Equal {
-            Some(self.step.cmp(&other.step))
-        } else {
-            Some(walk)
-        }
-    }
-}
-
 /// An annotation connecting a protocol to the weight that it added to an
 /// edge.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -254,7 +353,27 @@ impl From<Vec<&'static str>> for ProtocolStack {
 
 #[cfg(test)]
 mod tests {
-    use super::{Appearance, ProtocolStack};
+    use super::{
+        Appearance, Edge, EdgeAnnotations, ProtocolAnnotation, ProtocolStack, Response, WeightMerge,
+    };
+    use crate::fleet::Merge;
+    use crate::Oid;
+
+    fn edge(from: u64, to: u64, weight: usize) -> Edge {
+        Edge {
+            from: Oid::new(from),
+            to: Oid::new(to),
+            weight,
+            annotations: Default::default(),
+        }
+    }
+
+    fn http(weight: u32) -> ProtocolAnnotation {
+        ProtocolAnnotation {
+            weight,
+            protocol: ProtocolStack::from(vec!["IPv4", "TCP", "HTTP"]),
+        }
+    }
 
     #[test]
     fn protocol_fmt_http() {
@@ -299,4 +418,96 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn merge_responses() {
+        let mut first = Response {
+            from: 1_000,
+            until: 2_000,
+            edges: vec![
+                Edge {
+                    annotations: EdgeAnnotations {
+                        appearances: Some(vec![Appearance::new(0, 1)]),
+                        protocols: Some(vec![http(10)]),
+                    },
+                    ..edge(1, 2, 10)
+                },
+                edge(2, 3, 5),
+            ],
+            ..Default::default()
+        };
+
+        let second = Response {
+            from: 2_000,
+            until: 3_000,
+            warnings: vec![super::Error::new("Too many edges", "truncated")],
+            edges: vec![
+                edge(4, 5, 1),
+                Edge {
+                    annotations: EdgeAnnotations {
+                        appearances: Some(vec![Appearance::new(0, 0), Appearance::new(0, 1)]),
+                        protocols: Some(vec![
+                            http(20),
+                            ProtocolAnnotation {
+                                weight: 3,
+                                protocol: ProtocolStack::from(vec!["IPv4", "UDP", "DNS"]),
+                            },
+                        ]),
+                    },
+                    ..edge(1, 2, 20)
+                },
+            ],
+        };
+
+        let mut max = first.clone();
+        max.merge_with(second.clone(), WeightMerge::Max);
+        assert_eq!(20, max.edges[0].weight);
+        assert_eq!(
+            Some(20),
+            max.edges[0]
+                .annotations
+                .protocols
+                .as_ref()
+                .map(|p| p[0].weight)
+        );
+
+        let mut concatenated = first.clone();
+        concatenated.merge(second.clone());
+        assert_eq!(4, concatenated.edges.len());
+        assert_eq!((1_000, 3_000), (concatenated.from, concatenated.until));
+
+        first.merge_with(second, WeightMerge::Sum);
+        assert_eq!((1_000, 3_000), (first.from, first.until));
+        assert_eq!(1, first.warnings.len());
+        assert_eq!(
+            vec![(1, 2), (2, 3), (4, 5)],
+            first
+                .iter()
+                .map(|edge| (edge.from.get(), edge.to.get()))
+                .collect::<Vec<_>>()
+        );
+
+        let merged = &first.edges[0];
+        assert_eq!(30, merged.weight);
+        assert_eq!(
+            Some(vec![Appearance::new(0, 0), Appearance::new(0, 1)]),
+            merged.annotations.appearances
+        );
+
+        let protocols = merged.annotations.protocols.as_ref().unwrap();
+        assert_eq!(2, protocols.len());
+        assert_eq!(http(30), protocols[0]);
+        assert_eq!("DNS", protocols[1].protocol.to_string());
+    }
+
+    #[test]
+    fn dedup_edges() {
+        let mut response = Response {
+            edges: vec![edge(1, 2, 1), edge(2, 1, 1), edge(1, 2, usize::MAX)],
+            ..Default::default()
+        };
+
+        response.dedup_edges(WeightMerge::Sum);
+        assert_eq!(vec![edge(1, 2, usize::MAX), edge(2, 1, 1)], response.edges);
+    }
 }
@@ -0,0 +1,619 @@
+//! Parsing and printing the text syntax for activity map queries.
+
+use super::query::{
+    ObjectType, Protocol, Query, Relationship, Role, Source, Step, Walk, WalkOrigin,
+};
+use crate::{Oid, QueryTime};
+use std::fmt;
+use std::iter::Peekable;
+use std::str::{CharIndices, FromStr};
+use thiserror::Error;
+
+/// An error encountered parsing the text syntax for a [`Query`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{message} at position {position}")]
+pub struct ParseQueryError {
+    /// The byte offset in the input where the problem was found.
+    pub position: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Arrow,
+    Colon,
+    Comma,
+    Semicolon,
+    Open,
+    Close,
+    Word(String),
+    Quoted(String),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Arrow => f.write_str("'->'"),
+            Token::Colon => f.write_str("':'"),
+            Token::Comma => f.write_str("','"),
+            Token::Semicolon => f.write_str("';'"),
+            Token::Open => f.write_str("'['"),
+            Token::Close => f.write_str("']'"),
+            Token::Word(word) => write!(f, "'{}'", word),
+            Token::Quoted(text) => write!(f, "{:?}", text),
+        }
+    }
+}
+
+/// Words with a special meaning in the syntax, which must be quoted to be used as protocols.
+const KEYWORDS: &[&str] = &["all", "any", "from", "in", "none", "not", "until"];
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '.'
+}
+
+/// Returns `true` if `word` would be read back as the same single word.
+fn is_plain_word(word: &str) -> bool {
+    !word.is_empty()
+        && word.chars().all(is_word_char)
+        && !word.contains("->")
+        && !KEYWORDS.contains(&word)
+}
+
+/// Write a protocol name, quoting it if it is not a plain word.
+fn write_protocol(f: &mut fmt::Formatter, protocol: &str) -> fmt::Result {
+    if is_plain_word(protocol) {
+        return f.write_str(protocol);
+    }
+
+    f.write_str("\"")?;
+    for c in protocol.chars() {
+        if c == '"' || c == '\\' {
+            f.write_str("\\")?;
+        }
+
+        write!(f, "{}", c)?;
+    }
+
+    f.write_str("\"")
+}
+
+/// Read a quoted string starting at the opening quote, returning its contents.
+fn read_quoted(chars: &mut Peekable<CharIndices>, start: usize) -> Result<String, ParseQueryError> {
+    chars.next();
+    let mut text = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok(text),
+            '\\' => match chars.next() {
+                Some((_, c @ '"')) | Some((_, c @ '\\')) => text.push(c),
+                _ => {
+                    return Err(ParseQueryError {
+                        position: i,
+                        message: "Expected '\"' or '\\' after '\\'".into(),
+                    })
+                }
+            },
+            c => text.push(c),
+        }
+    }
+
+    Err(ParseQueryError {
+        position: start,
+        message: "Unterminated quoted protocol".into(),
+    })
+}
+
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, ParseQueryError> {
+    let mut tokens = vec![];
+    let mut chars = input.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        let token = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            ':' => Token::Colon,
+            ',' => Token::Comma,
+            ';' => Token::Semicolon,
+            '[' => Token::Open,
+            ']' => Token::Close,
+            '"' => {
+                tokens.push((start, Token::Quoted(read_quoted(&mut chars, start)?)));
+                continue;
+            }
+            '-' if input[start..].starts_with("->") => {
+                chars.next();
+                Token::Arrow
+            }
+            c if is_word_char(c) => {
+                let mut end = start;
+                while let Some(&(i, c)) = chars.peek() {
+                    if !is_word_char(c) || input[i..].starts_with("->") {
+                        break;
+                    }
+
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+
+                tokens.push((start, Token::Word(input[start..end].to_string())));
+                continue;
+            }
+            other => {
+                return Err(ParseQueryError {
+                    position: start,
+                    message: format!("Unexpected '{}'", other),
+                })
+            }
+        };
+
+        chars.next();
+        tokens.push((start, token));
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    input_len: usize,
+}
+
+impl Parser {
+    fn new(input: &str) -> Result<Self, ParseQueryError> {
+        Ok(Self {
+            tokens: tokenize(input)?,
+            next: 0,
+            input_len: input.len(),
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(_, token)| token)
+    }
+
+    fn error(&self, expected: &str) -> ParseQueryError {
+        match self.tokens.get(self.next) {
+            Some((position, token)) => ParseQueryError {
+                position: *position,
+                message: format!("Expected {}, found {}", expected, token),
+            },
+            None => ParseQueryError {
+                position: self.input_len,
+                message: format!("Expected {}, found end of input", expected),
+            },
+        }
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.next += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(w)) if w == word => {
+                self.next += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), ParseQueryError> {
+        if self.eat(&token) {
+            Ok(())
+        } else {
+            Err(self.error(&token.to_string()))
+        }
+    }
+
+    /// Consume a word, passing it to `convert`. If `convert` fails, the error points at the
+    /// word and says what was expected instead.
+    fn word<T>(
+        &mut self,
+        expected: &str,
+        convert: impl FnOnce(&str) -> Option<T>,
+    ) -> Result<T, ParseQueryError> {
+        let value = match self.peek() {
+            Some(Token::Word(word)) => convert(word),
+            _ => None,
+        };
+
+        match value {
+            Some(value) => {
+                self.next += 1;
+                Ok(value)
+            }
+            None => Err(self.error(expected)),
+        }
+    }
+
+    fn query(&mut self) -> Result<Query, ParseQueryError> {
+        let mut query = Query::default();
+        if self.peek().is_none() {
+            return Ok(query);
+        }
+
+        loop {
+            if self.eat_word("from") {
+                query.from = self.time()?;
+            } else if self.eat_word("until") {
+                query.until = self.time()?;
+            } else {
+                query.walks.push(self.walk()?);
+            }
+
+            if self.peek().is_none() {
+                return Ok(query);
+            }
+
+            self.expect(Token::Semicolon)?;
+
+            // Allow a trailing separator.
+            if self.peek().is_none() {
+                return Ok(query);
+            }
+        }
+    }
+
+    fn time(&mut self) -> Result<QueryTime, ParseQueryError> {
+        self.word("a query time such as '-30m'", |word| word.parse().ok())
+    }
+
+    fn walk(&mut self) -> Result<Walk, ParseQueryError> {
+        let origins = if self.eat_word("all") {
+            WalkOrigin::All
+        } else if self.eat_word("none") {
+            WalkOrigin::Specific(vec![])
+        } else {
+            WalkOrigin::Specific(self.sources()?)
+        };
+
+        let mut steps = vec![];
+        while self.eat(&Token::Arrow) {
+            steps.push(self.step()?);
+        }
+
+        Ok(Walk { origins, steps })
+    }
+
+    fn sources(&mut self) -> Result<Vec<Source>, ParseQueryError> {
+        let mut sources = vec![self.source()?];
+        while self.eat(&Token::Comma) {
+            sources.push(self.source()?);
+        }
+
+        Ok(sources)
+    }
+
+    fn source(&mut self) -> Result<Source, ParseQueryError> {
+        let object_type =
+            self.word(
+                "'all', 'none', or a source such as 'group:1'",
+                |word| match word {
+                    "device" => Some(ObjectType::Device),
+                    "group" => Some(ObjectType::DeviceGroup),
+                    "activity_group" => Some(ObjectType::ActivityGroup),
+                    _ => None,
+                },
+            )?;
+        self.expect(Token::Colon)?;
+        let id: Oid = self.word("a numeric ID", |word| word.parse().ok())?;
+
+        Ok(Source::new(object_type, id))
+    }
+
+    fn step(&mut self) -> Result<Step, ParseQueryError> {
+        // A bare `any` means the step's relationships are not restricted at all, while `any`
+        // followed by a role or another relationship is a relationship with any protocol.
+        let unrestricted = self.peek() == Some(&Token::Word("any".into()))
+            && !matches!(
+                self.tokens.get(self.next + 1),
+                Some((_, Token::Colon)) | Some((_, Token::Comma))
+            );
+
+        let mut relationships = vec![];
+        if unrestricted {
+            self.next += 1;
+        } else {
+            relationships.push(self.relationship()?);
+            while self.eat(&Token::Comma) {
+                relationships.push(self.relationship()?);
+            }
+        }
+
+        let mut step = Step {
+            relationships,
+            ..Default::default()
+        };
+
+        while self.eat(&Token::Open) {
+            let exclude = self.eat_word("not");
+            if !self.eat_word("in") {
+                return Err(self.error(if exclude { "'in'" } else { "'in' or 'not in'" }));
+            }
+
+            let sources = self.sources()?;
+            self.expect(Token::Close)?;
+
+            if exclude {
+                step.peer_not_in.extend(sources);
+            } else {
+                step.peer_in.extend(sources);
+            }
+        }
+
+        Ok(step)
+    }
+
+    fn relationship(&mut self) -> Result<Relationship, ParseQueryError> {
+        let protocol = match self.peek() {
+            Some(Token::Quoted(protocol)) => {
+                let protocol = Some(Protocol::from(protocol.as_str()));
+                self.next += 1;
+                protocol
+            }
+            _ => self.word("a protocol or 'any'", |word| match word {
+                "any" => Some(None),
+                protocol => Some(Some(Protocol::from(protocol))),
+            })?,
+        };
+
+        let role = if self.eat(&Token::Colon) {
+            self.word("'client', 'server', or 'any'", |word| match word {
+                "client" => Some(Role::Client),
+                "server" => Some(Role::Server),
+                "any" => Some(Role::Any),
+                _ => None,
+            })?
+        } else {
+            Role::Any
+        };
+
+        Ok(Relationship { role, protocol })
+    }
+}
+
+/// Parse a query from the text syntax described in the [module docs](super#text-syntax).
+///
+/// The query is not validated; call [`Query::validate`] to check it.
+impl FromStr for Query {
+    type Err = ParseQueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Parser::new(s)?.query()
+    }
+}
+
+/// Write a comma-separated list.
+fn write_list<T: fmt::Display>(f: &mut fmt::Formatter, items: &[T]) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+
+        item.fmt(f)?;
+    }
+
+    Ok(())
+}
+
+/// Print the query in the text syntax described in the [module docs](super#text-syntax).
+///
+/// `from` and `until` are left out when they are "now", so a default query prints as an
+/// empty string.
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut statements = vec![];
+        for (keyword, time) in &[("from", &self.from), ("until", &self.until)] {
+            let time = time.to_string();
+            if time != "0" {
+                statements.push(format!("{} {}", keyword, time));
+            }
+        }
+
+        statements.extend(self.walks.iter().map(Walk::to_string));
+        f.write_str(&statements.join("; "))
+    }
+}
+
+impl fmt::Display for Walk {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.origins.fmt(f)?;
+        for step in &self.steps {
+            write!(f, " -> {}", step)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for WalkOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WalkOrigin::All => f.write_str("all"),
+            WalkOrigin::Specific(sources) if sources.is_empty() => f.write_str("none"),
+            WalkOrigin::Specific(sources) => write_list(f, sources),
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let object_type = match self.object_type {
+            ObjectType::Device => "device",
+            ObjectType::DeviceGroup => "group",
+            ObjectType::ActivityGroup => "activity_group",
+        };
+
+        write!(f, "{}:{}", object_type, self.object_id)
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.relationships.is_empty() {
+            f.write_str("any")?;
+        } else {
+            write_list(f, &self.relationships)?;
+        }
+
+        if !self.peer_in.is_empty() {
+            f.write_str(" [in ")?;
+            write_list(f, &self.peer_in)?;
+            f.write_str("]")?;
+        }
+
+        if !self.peer_not_in.is_empty() {
+            f.write_str(" [not in ")?;
+            write_list(f, &self.peer_not_in)?;
+            f.write_str("]")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Relationship {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Any protocol is always written with its role, since a bare `any` means the step's
+        // relationships are not restricted.
+        match &self.protocol {
+            Some(protocol) => write_protocol(f, &protocol.to_string())?,
+            None => f.write_str("any")?,
+        }
+
+        match (self.role, &self.protocol) {
+            (Role::Any, Some(_)) => Ok(()),
+            (Role::Any, None) => f.write_str(":any"),
+            (Role::Client, _) => f.write_str(":client"),
+            (Role::Server, _) => f.write_str(":server"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ParseQueryError;
+    use crate::activitymap::query::{strategies, Relationship, Role};
+    use crate::activitymap::{Query, Source, Step, Walk, WalkOrigin};
+    use crate::Oid;
+    use proptest::prelude::*;
+
+    #[test]
+    fn parse() {
+        let query: Query = "group:1 -> http:server -> any [not in group:7]"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            vec![Walk {
+                origins: WalkOrigin::Specific(vec![Source::device_group(Oid::new(1))]),
+                steps: vec![
+                    Step {
+                        relationships: vec![Relationship::new("http", Role::Server)],
+                        ..Default::default()
+                    },
+                    Step {
+                        peer_not_in: vec![Source::device_group(Oid::new(7))],
+                        ..Default::default()
+                    },
+                ],
+            }],
+            query.walks
+        );
+    }
+
+    #[test]
+    fn print_protocols() {
+        let step = |protocol: &str, role| Step {
+            relationships: vec![Relationship::new(protocol, role)],
+            ..Default::default()
+        };
+
+        let query = Query::from(Walk {
+            origins: WalkOrigin::Specific(vec![]),
+            steps: vec![
+                step("any", Role::Server),
+                step("SSL/TLS", Role::Any),
+                step("HTTP 2", Role::Client),
+                step("say \"hi\\", Role::Any),
+                step("MS-SQL", Role::Any),
+                Step {
+                    relationships: vec![Relationship::default()],
+                    ..Default::default()
+                },
+                Step::default(),
+            ],
+        });
+
+        assert_eq!(
+            r#"none -> "any":server -> "SSL/TLS" -> "HTTP 2":client -> "say \"hi\\" -> MS-SQL -> any:any -> any"#,
+            query.to_string()
+        );
+
+        let reparsed: Query = query.to_string().parse().unwrap();
+        assert_eq!(query.walks, reparsed.walks);
+        assert_eq!("", Query::default().to_string());
+        assert_eq!(0, "".parse::<Query>().unwrap().walks.len());
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(query in strategies::query()) {
+            let text = query.to_string();
+            let parsed: Query = text.parse().unwrap();
+
+            prop_assert_eq!(query.from.to_string(), parsed.from.to_string());
+            prop_assert_eq!(query.until.to_string(), parsed.until.to_string());
+            prop_assert_eq!(&query.walks, &parsed.walks);
+            prop_assert_eq!(text, parsed.to_string());
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |input: &str| input.parse::<Query>().unwrap_err();
+
+        assert_eq!(
+            ParseQueryError {
+                position: 0,
+                message: "Expected 'all', 'none', or a source such as 'group:1', found 'grp'"
+                    .into(),
+            },
+            error("grp:1 -> any")
+        );
+        assert_eq!(
+            ParseQueryError {
+                position: 19,
+                message: "Expected 'client', 'server', or 'any', found 'srv'".into(),
+            },
+            error("device:15 -> http: srv")
+        );
+        assert_eq!(
+            ParseQueryError {
+                position: 22,
+                message: "Expected ']', found end of input".into(),
+            },
+            error("all -> any [in group:1")
+        );
+        assert_eq!(
+            "Expected a query time such as '-30m', found 'soon' at position 5",
+            error("from soon; all -> any").to_string()
+        );
+        assert_eq!(
+            ParseQueryError {
+                position: 7,
+                message: "Unterminated quoted protocol".into(),
+            },
+            error(r#"all -> "HTTP"#)
+        );
+    }
+}
@@ -0,0 +1,337 @@
+//! Typed operations for `/api/v1/alerts`.
+//!
+//! # Keeping Alerts in Source Control
+//! [`export_to_file`] writes the alert configurations on an appliance to a JSON file, and
+//! [`sync_file`] makes another appliance match that file. Properties not modeled by
+//! [`AlertDefinition`], such as trend alert settings, are preserved in
+//! [`AlertDefinition::settings`].
+
+use crate::api_response::created_id;
+use crate::sync::{Change, Definition, Plan, Remote, SyncOptions, SyncReport};
+use crate::{ApiResponse, Assignment, Client, Error};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// The kind of condition which causes an alert to fire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertType {
+    /// Fires when a metric crosses a fixed value.
+    Threshold,
+    /// Fires when a metric deviates from its historical trend.
+    Trend,
+}
+
+/// The user-controlled properties of an alert configuration, used to create or replace an
+/// alert.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(rename = "type")]
+    pub alert_type: AlertType,
+    #[serde(default)]
+    pub disabled: bool,
+    /// Whether the alert applies to all compatible sources rather than only those it has been
+    /// assigned to.
+    #[serde(default)]
+    pub apply_all: bool,
+    /// The metric category, such as `extrahop.device.http_server`.
+    pub stat_name: String,
+    /// The metric within the category, such as `rsp_error`.
+    pub field_name: String,
+    /// The comparison applied to the metric, such as `>`.
+    pub operator: String,
+    /// The value the metric is compared against.
+    pub operand: Value,
+    /// The time over which the metric is aggregated, in seconds.
+    pub interval_length: u32,
+    /// The minimum time between repeated notifications, in seconds.
+    pub refire_interval: u32,
+    /// The severity of the alert, from 0 (emergency) to 7 (debug).
+    pub severity: u8,
+    /// Any other properties of the alert, such as trend settings or a second metric for ratio
+    /// alerts.
+    #[serde(flatten)]
+    pub settings: BTreeMap<String, Value>,
+}
+
+impl AlertDefinition {
+    /// Returns `true` if the alert on the appliance already matches this definition.
+    ///
+    /// Only the settings present in the definition are compared, since the appliance fills in
+    /// defaults for any settings that are not sent.
+    pub fn matches(&self, alert: &Alert) -> bool {
+        let remote = &alert.definition;
+        self.name == remote.name
+            && self.description == remote.description
+            && self.alert_type == remote.alert_type
+            && self.disabled == remote.disabled
+            && self.apply_all == remote.apply_all
+            && self.stat_name == remote.stat_name
+            && self.field_name == remote.field_name
+            && self.operator == remote.operator
+            && self.operand == remote.operand
+            && self.interval_length == remote.interval_length
+            && self.refire_interval == remote.refire_interval
+            && self.severity == remote.severity
+            && self
+                .settings
+                .iter()
+                .all(|(key, value)| remote.settings.get(key) == Some(value))
+    }
+}
+
+/// An alert configuration as returned by the REST API.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Alert {
+    pub id: u64,
+    #[serde(default)]
+    pub author: Option<String>,
+    /// The time the alert was last modified, in milliseconds since epoch.
+    #[serde(default)]
+    pub mod_time: Option<u64>,
+    #[serde(flatten)]
+    pub definition: AlertDefinition,
+}
+
+impl Definition<Alert> for AlertDefinition {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn matches(&self, remote: &Alert) -> bool {
+        AlertDefinition::matches(self, remote)
+    }
+}
+
+impl Remote for Alert {
+    fn name(&self) -> &str {
+        &self.definition.name
+    }
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+/// Get all alert configurations on the appliance.
+pub async fn list(client: &Client) -> Result<Vec<Alert>, Error> {
+    client
+        .get("v1/alerts")?
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Get a single alert configuration by ID.
+pub async fn get(client: &Client, id: u64) -> Result<Alert, Error> {
+    client
+        .get(&format!("v1/alerts/{}", id))?
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Create a new alert configuration, returning the ID assigned by the appliance.
+pub async fn create(client: &Client, definition: &AlertDefinition) -> Result<u64, Error> {
+    let response = client
+        .post("v1/alerts")?
+        .json(definition)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    created_id(&response)
+}
+
+/// Overwrite the properties of an existing alert configuration with those in `definition`.
+pub async fn update(client: &Client, id: u64, definition: &AlertDefinition) -> Result<(), Error> {
+    client
+        .patch(&format!("v1/alerts/{}", id))?
+        .json(definition)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+/// Delete an alert configuration.
+pub async fn delete(client: &Client, id: u64) -> Result<(), Error> {
+    client
+        .delete(&format!("v1/alerts/{}", id))?
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+/// Assign an alert to, or unassign it from, specific devices.
+pub async fn assign_devices(client: &Client, id: u64, change: &Assignment) -> Result<(), Error> {
+    client
+        .post(&format!("v1/alerts/{}/devices", id))?
+        .json(change)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+/// Assign an alert to, or unassign it from, device groups.
+pub async fn assign_device_groups(
+    client: &Client,
+    id: u64,
+    change: &Assignment,
+) -> Result<(), Error> {
+    client
+        .post(&format!("v1/alerts/{}/devicegroups", id))?
+        .json(change)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+/// Assign an alert to the devices and device groups in `sources`.
+///
+/// Activity groups cannot have alerts assigned to them, so they are ignored.
+#[cfg(feature = "topology")]
+pub async fn assign_sources(
+    client: &Client,
+    id: u64,
+    sources: &[crate::activitymap::Source],
+) -> Result<(), Error> {
+    use crate::activitymap::query::ObjectType;
+
+    let ids_of = |object_type| {
+        sources
+            .iter()
+            .filter(move |source| source.object_type == object_type)
+            .map(|source| source.object_id)
+    };
+
+    let devices = Assignment::assign(ids_of(ObjectType::Device));
+    if !devices.is_empty() {
+        assign_devices(client, id, &devices).await?;
+    }
+
+    let groups = Assignment::assign(ids_of(ObjectType::DeviceGroup));
+    if !groups.is_empty() {
+        assign_device_groups(client, id, &groups).await?;
+    }
+
+    Ok(())
+}
+
+/// Write the definitions of every alert on the appliance to a JSON file, sorted by name.
+///
+/// Built-in properties such as the ID and author are not included, so the file can be used
+/// with [`sync_file`] on any appliance.
+pub async fn export_to_file(client: &Client, path: impl AsRef<Path>) -> Result<(), Error> {
+    let mut definitions: Vec<_> = list(client)
+        .await?
+        .into_iter()
+        .map(|alert| alert.definition)
+        .collect();
+    definitions.sort_by(|a, b| a.name.cmp(&b.name));
+
+    tokio::fs::write(path, serde_json::to_vec_pretty(&definitions)?).await?;
+    Ok(())
+}
+
+/// Read alert definitions from a JSON file written by [`export_to_file`].
+pub async fn load_file(path: impl AsRef<Path>) -> Result<Vec<AlertDefinition>, Error> {
+    Ok(serde_json::from_slice(&tokio::fs::read(path).await?)?)
+}
+
+/// Make the alerts on the appliance match `definitions`.
+///
+/// Alerts are matched by name, as described in the [`sync`](crate::sync) module. A failure
+/// to change one alert does not prevent the others from being changed; see
+/// [`SyncReport::failed`].
+pub async fn sync(
+    client: &Client,
+    definitions: &[AlertDefinition],
+    options: &SyncOptions,
+) -> Result<SyncReport, Error> {
+    let mut plan = Plan::new(definitions, list(client).await?, options);
+    let mut report = plan.report();
+
+    for (name, change) in plan.changes {
+        let result = match (&change, options.dry_run) {
+            (_, true) => Ok(()),
+            (Change::Create(definition), false) => create(client, definition).await.map(|_| ()),
+            (Change::Update(id, definition), false) => update(client, *id, definition).await,
+            (Change::Delete(id), false) => delete(client, *id).await,
+        };
+
+        report.record(name, &change, result);
+    }
+
+    Ok(report)
+}
+
+/// Make the alerts on the appliance match the definitions in a file written by
+/// [`export_to_file`].
+pub async fn sync_file(
+    client: &Client,
+    path: impl AsRef<Path>,
+    options: &SyncOptions,
+) -> Result<SyncReport, Error> {
+    sync(client, &load_file(path).await?, options).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Alert, AlertType};
+    use serde_json::json;
+
+    #[test]
+    fn round_trip_settings() {
+        let body = json!({
+            "id": 7,
+            "author": "setup",
+            "name": "HTTP Errors",
+            "type": "trend",
+            "stat_name": "extrahop.device.http_server",
+            "field_name": "rsp_error",
+            "operator": ">",
+            "operand": 10,
+            "interval_length": 300,
+            "refire_interval": 1800,
+            "severity": 3,
+            "trend_type": "hour_of_day"
+        });
+
+        let alert: Alert = serde_json::from_value(body.clone()).unwrap();
+        assert_eq!(AlertType::Trend, alert.definition.alert_type);
+        assert_eq!(
+            Some(&json!("hour_of_day")),
+            alert.definition.settings.get("trend_type")
+        );
+        assert!(!alert.definition.settings.contains_key("id"));
+        assert!(alert.definition.matches(&alert));
+
+        let mut expected = body;
+        expected["description"] = json!("");
+        expected["disabled"] = json!(false);
+        expected["apply_all"] = json!(false);
+        expected["mod_time"] = json!(null);
+        assert_eq!(expected, serde_json::to_value(&alert).unwrap());
+    }
+}
@@ -0,0 +1,55 @@
+//! Typed operations for `/api/v1/apikeys`.
+//!
+//! To list the keys belonging to a single user, see [`users::api_keys`](crate::users::api_keys).
+
+use crate::{ApiResponse, Client, Error};
+use serde::{Deserialize, Serialize};
+
+/// An API key as returned by the REST API. The key itself is never returned after creation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ApiKey {
+    pub id: u64,
+    /// The user on whose behalf the key makes requests.
+    pub username: String,
+    #[serde(default)]
+    pub description: String,
+    /// The time the key was created, in milliseconds since epoch.
+    #[serde(default)]
+    pub time_added: Option<u64>,
+    /// The last few characters of the key, to help identify it.
+    #[serde(default)]
+    pub key_suffix: Option<String>,
+}
+
+/// Get all API keys on the appliance.
+pub async fn list(client: &Client) -> Result<Vec<ApiKey>, Error> {
+    client
+        .get("v1/apikeys")?
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Get a single API key by ID.
+pub async fn get(client: &Client, id: u64) -> Result<ApiKey, Error> {
+    client
+        .get(&format!("v1/apikeys/{}", id))?
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Revoke an API key. Requests made with the key will be rejected immediately.
+pub async fn revoke(client: &Client, id: u64) -> Result<(), Error> {
+    client
+        .delete(&format!("v1/apikeys/{}", id))?
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
@@ -1,6 +1,6 @@
 use crate::{Error, RestError};
 use async_trait::async_trait;
-use reqwest::Response;
+use reqwest::{header, Response};
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
 
@@ -44,3 +44,26 @@ impl ApiResponse for Response {
             .map_err(Error::from)
     }
 }
+
+/// Get the last path segment of the `Location` header of a `201 Created` or `202 Accepted`
+/// response.
+///
+/// The API returns locations such as `/api/v1/devicegroups/12` or `/api/v1/jobs/{uuid}`, so
+/// the last segment identifies the created object or job.
+pub(crate) fn location_id(response: &Response) -> Result<&str, Error> {
+    response
+        .headers()
+        .get(header::LOCATION)
+        .and_then(|location| location.to_str().ok())
+        .and_then(|location| location.trim_end_matches('/').rsplit('/').next())
+        .filter(|id| !id.is_empty())
+        .ok_or(Error::MissingLocation)
+}
+
+/// Get the numeric ID of a newly-created object from the `Location` header of a
+/// `201 Created` response.
+pub(crate) fn created_id(response: &Response) -> Result<u64, Error> {
+    location_id(response)?
+        .parse()
+        .map_err(|_| Error::MissingLocation)
+}
@@ -0,0 +1,58 @@
+//! Typed access to `/api/v1/appliances`, which lists the appliances connected to the one
+//! being called.
+//!
+//! To send requests to the connected appliances themselves, create an
+//! [`Appliance`](crate::client::Appliance) client for each and combine them in a
+//! [`Fleet`](crate::fleet::Fleet).
+
+use crate::{ApiResponse, Client, Error};
+use serde::{Deserialize, Serialize};
+
+/// An appliance connected to the one being called, or the called appliance itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ConnectedAppliance {
+    pub id: u64,
+    #[serde(default)]
+    pub uuid: Option<String>,
+    #[serde(default)]
+    pub hostname: Option<String>,
+    #[serde(default)]
+    pub nickname: Option<String>,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    /// The kind of appliance, such as `extrahop` (a sensor) or `command`.
+    #[serde(default)]
+    pub platform: Option<String>,
+    #[serde(default)]
+    pub firmware_version: Option<String>,
+    /// How the appliance is connected, such as `local`, `direct`, or `cloud`.
+    #[serde(default)]
+    pub connection_type: Option<String>,
+    /// A human-friendly description of the connection's health.
+    #[serde(default)]
+    pub status_message: Option<String>,
+    /// Whether the called appliance manages this one.
+    #[serde(default)]
+    pub managed_by_local: bool,
+    /// Whether this appliance manages the called appliance.
+    #[serde(default)]
+    pub manages_local: bool,
+}
+
+impl ConnectedAppliance {
+    /// Returns `true` if this entry describes the appliance being called.
+    pub fn is_local(&self) -> bool {
+        self.connection_type.as_deref() == Some("local")
+    }
+}
+
+/// Get the appliances connected to the one being called, including itself.
+pub async fn list(client: &Client) -> Result<Vec<ConnectedAppliance>, Error> {
+    client
+        .get("v1/appliances")?
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
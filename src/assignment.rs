@@ -0,0 +1,73 @@
+use crate::Oid;
+use serde::Serialize;
+
+/// Objects to associate with and dissociate from another object, such as devices to add to
+/// a static device group or devices to which a trigger should be assigned.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Assignment {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub assign: Vec<Oid>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unassign: Vec<Oid>,
+}
+
+impl Assignment {
+    /// Create an assignment which only associates the specified objects.
+    pub fn assign<K>(ids: impl IntoIterator<Item = Oid<K>>) -> Self {
+        Self {
+            assign: ids.into_iter().map(Oid::untyped).collect(),
+            unassign: vec![],
+        }
+    }
+
+    /// Create an assignment which only dissociates the specified objects.
+    pub fn unassign<K>(ids: impl IntoIterator<Item = Oid<K>>) -> Self {
+        Self {
+            assign: vec![],
+            unassign: ids.into_iter().map(Oid::untyped).collect(),
+        }
+    }
+
+    /// Returns `true` if the assignment would not change any associations.
+    pub fn is_empty(&self) -> bool {
+        self.assign.is_empty() && self.unassign.is_empty()
+    }
+
+    /// Split the assignment into requests of at most `size` objects each.
+    ///
+    /// Assignments are sent before unassignments, so an object which appears in both lists
+    /// ends up dissociated, matching the behavior of a single request.
+    pub(crate) fn batches(&self, size: usize) -> Vec<Assignment> {
+        let assign = self.assign.chunks(size).map(|ids| Assignment {
+            assign: ids.to_vec(),
+            unassign: vec![],
+        });
+
+        let unassign = self.unassign.chunks(size).map(|ids| Assignment {
+            assign: vec![],
+            unassign: ids.to_vec(),
+        });
+
+        assign.chain(unassign).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Assignment;
+    use crate::Oid;
+
+    #[test]
+    fn batches() {
+        let change = Assignment {
+            assign: (0..5).map(Oid::new).collect(),
+            unassign: vec![Oid::new(9)],
+        };
+
+        let batches = change.batches(2);
+        assert_eq!(4, batches.len());
+        assert_eq!(vec![Oid::new(4)], batches[2].assign);
+        assert_eq!(vec![Oid::new(9)], batches[3].unassign);
+        assert!(batches.iter().all(|batch| !batch.is_empty()));
+    }
+}
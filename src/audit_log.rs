@@ -0,0 +1,288 @@
+//! Typed access to `/api/v1/auditlog`.
+//!
+//! Entries are retrieved a page at a time; [`entries`] follows the pages as a `Stream`, and
+//! [`export`] writes every entry in a window to a file as JSON lines or CSV.
+//!
+//! The endpoint does not filter by time, so the window in an [`AuditLogQuery`] is applied to
+//! each entry's `occur_time` after it is received. Every page is still requested.
+//!
+//! # Example
+//! ```rust,ignore
+//! use extrahop::audit_log::{self, AuditLogQuery, ExportFormat};
+//!
+//! let query = AuditLogQuery {
+//!     from: Some("-1w".into()),
+//!     ..Default::default()
+//! };
+//!
+//! let mut file = tokio::fs::File::create("audit.csv").await?;
+//! let count = audit_log::export(&client, &query, ExportFormat::Csv, &mut file).await?;
+//! println!("Exported {} entries", count);
+//! ```
+
+use crate::query_time::now_millis;
+use crate::{paging, ApiResponse, Client, Error, ParseQueryTimeError, QueryRange, QueryTime};
+use futures::future::{self, TryFutureExt};
+use futures::stream::{Stream, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// The number of entries requested per page by [`entries`] when the query does not set a
+/// limit.
+pub const DEFAULT_PAGE_SIZE: usize = 1000;
+
+/// A single audit log message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct AuditLogEntry {
+    pub id: u64,
+    /// The time the event occurred, in milliseconds since epoch.
+    pub occur_time: u64,
+    pub body: AuditLogBody,
+}
+
+/// The description of an audited event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct AuditLogBody {
+    /// The user who performed the operation.
+    #[serde(default)]
+    pub user: String,
+    /// The area of the system affected, such as `Dashboard` or `Trigger`.
+    #[serde(default)]
+    pub component: String,
+    /// The operation performed, such as `Modify` or `Login`.
+    #[serde(default)]
+    pub operation: String,
+    #[serde(default)]
+    pub details: String,
+}
+
+/// The window and page of audit log entries to retrieve.
+///
+/// Only `limit` and `offset` are sent to the appliance; the window is applied by [`entries`]
+/// and [`export`].
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AuditLogQuery {
+    /// The earliest time for which entries should be returned. Relative times are resolved
+    /// against the local clock.
+    #[serde(skip)]
+    pub from: Option<QueryTime>,
+    /// The time before which entries should be returned. Relative times are resolved against
+    /// the local clock.
+    #[serde(skip)]
+    pub until: Option<QueryTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+}
+
+/// The file format written by [`export`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One JSON object per line, in the same shape as [`AuditLogEntry`].
+    JsonLines,
+    /// Comma-separated values with a header row. Each entry's body is flattened into columns.
+    Csv,
+}
+
+/// The columns written by [`ExportFormat::Csv`].
+const CSV_HEADER: &str = "id,occur_time,user,component,operation,details\n";
+
+impl AuditLogQuery {
+    /// Resolve the query's window against `now`, in milliseconds since epoch. Unset ends of
+    /// the window are unbounded, and a window which ends before it starts is an error.
+    fn window(&self, now: u64) -> Result<QueryRange, ParseQueryTimeError> {
+        let from = match &self.from {
+            Some(from) => from.resolve(now)?,
+            None => 0,
+        };
+
+        let until = match &self.until {
+            Some(until) => until.resolve(now)?,
+            None => u64::MAX,
+        };
+
+        QueryRange::ordered(from, until)
+    }
+}
+
+impl AuditLogEntry {
+    fn to_csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{}\n",
+            self.id,
+            self.occur_time,
+            csv_field(&self.body.user),
+            csv_field(&self.body.component),
+            csv_field(&self.body.operation),
+            csv_field(&self.body.details),
+        )
+    }
+}
+
+/// Get a single page of audit log entries.
+///
+/// The query's window is not applied; see [`entries`].
+pub async fn get(client: &Client, query: &AuditLogQuery) -> Result<Vec<AuditLogEntry>, Error> {
+    client
+        .get("v1/auditlog")?
+        .query(query)
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Get every audit log entry in the query's window, requesting more pages as the stream is
+/// consumed.
+///
+/// The query's `limit` is used as the page size, defaulting to [`DEFAULT_PAGE_SIZE`] when
+/// unset or 0, and its `offset` is used as the starting position. Entries outside the window
+/// are skipped. The stream ends after the first page that is not full, or after the first
+/// error.
+pub fn entries<'a>(
+    client: &'a Client,
+    query: &'a AuditLogQuery,
+) -> impl Stream<Item = Result<AuditLogEntry, Error>> + 'a {
+    future::ready(query.window(now_millis()))
+        .err_into::<Error>()
+        .map_ok(move |window| {
+            paging::pages(
+                query.limit,
+                query.offset,
+                DEFAULT_PAGE_SIZE,
+                move |limit, offset| {
+                    let page = AuditLogQuery {
+                        limit: Some(limit),
+                        offset: Some(offset),
+                        ..query.clone()
+                    };
+
+                    async move { get(client, &page).await }
+                },
+            )
+            .try_filter(move |entry| future::ready(window.contains(entry.occur_time)))
+        })
+        .try_flatten_stream()
+}
+
+/// Write every audit log entry in the query's window to `writer`, returning the number of
+/// entries written.
+///
+/// Entries are written as each page arrives, so memory use does not grow with the size of
+/// the log. The writer is flushed, but not shut down, before this function returns.
+pub async fn export<W>(
+    client: &Client,
+    query: &AuditLogQuery,
+    format: ExportFormat,
+    writer: &mut W,
+) -> Result<usize, Error>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    if format == ExportFormat::Csv {
+        writer.write_all(CSV_HEADER.as_bytes()).await?;
+    }
+
+    let mut count = 0;
+    let mut entries = Box::pin(entries(client, query));
+    while let Some(entry) = entries.try_next().await? {
+        let line = match format {
+            ExportFormat::JsonLines => {
+                let mut line = serde_json::to_string(&entry)?;
+                line.push('\n');
+                line
+            }
+            ExportFormat::Csv => entry.to_csv_row(),
+        };
+
+        writer.write_all(line.as_bytes()).await?;
+        count += 1;
+    }
+
+    writer.flush().await?;
+
+    Ok(count)
+}
+
+/// Quote a CSV field if it contains a delimiter, quote, or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuditLogBody, AuditLogEntry, AuditLogQuery};
+
+    #[test]
+    fn csv_row() {
+        let entry = AuditLogEntry {
+            id: 7,
+            occur_time: 1_600_000_000_000,
+            body: AuditLogBody {
+                user: "setup".into(),
+                component: "Dashboard".into(),
+                operation: "Modify".into(),
+                details: "Renamed \"Web\", then shared".into(),
+            },
+        };
+
+        assert_eq!(
+            "7,1600000000000,setup,Dashboard,Modify,\"Renamed \"\"Web\"\", then shared\"\n",
+            entry.to_csv_row()
+        );
+    }
+
+    #[test]
+    fn window() {
+        let now = 10_000_000;
+        let query = AuditLogQuery {
+            from: Some("-1h".into()),
+            until: Some(9_000_000u64.into()),
+            ..Default::default()
+        };
+
+        let window = query.window(now).unwrap();
+        assert!(!window.contains(6_399_999));
+        assert!(window.contains(6_400_000));
+        assert!(window.contains(8_999_999));
+        assert!(!window.contains(9_000_000));
+
+        let unbounded = AuditLogQuery::default().window(now).unwrap();
+        assert!(unbounded.contains(0));
+        assert!(unbounded.contains(now + 1));
+
+        let invalid = AuditLogQuery {
+            from: Some("-1 hour".into()),
+            ..Default::default()
+        };
+        assert!(invalid.window(now).is_err());
+
+        let reversed = AuditLogQuery {
+            from: Some("-1h".into()),
+            until: Some("-1d".into()),
+            ..Default::default()
+        };
+        assert!(reversed.window(now).is_err());
+    }
+
+    #[test]
+    fn window_not_sent() {
+        let query = AuditLogQuery {
+            from: Some("-1h".into()),
+            limit: Some(10),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            serde_json::json!({ "limit": 10 }),
+            serde_json::to_value(query).unwrap()
+        );
+    }
+}
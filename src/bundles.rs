@@ -0,0 +1,238 @@
+//! Typed operations for `/api/v1/bundles`.
+//!
+//! Bundles move customizations such as dashboards, triggers, and alerts between appliances.
+//! A typical migration creates a bundle on the source appliance, downloads it with
+//! [`download_to_file`], uploads it to the destination with [`upload_file`], and then applies
+//! it with [`apply_and_wait`].
+
+use crate::api_response::created_id;
+use crate::jobs::{self, Job, JobError, PollOptions};
+use crate::{ApiResponse, Client, Error};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+
+/// A bundle as returned by the REST API.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Bundle {
+    pub id: u64,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub built_in: bool,
+    /// The time the bundle was created, in milliseconds since epoch.
+    #[serde(default)]
+    pub created_time: Option<u64>,
+}
+
+/// A customization to include in a new bundle.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleObject {
+    /// The kind of object, such as `dashboard`, `trigger`, or `alert_config`.
+    pub object_type: String,
+    pub object_id: u64,
+}
+
+impl BundleObject {
+    /// Create a reference to an object of the specified type.
+    pub fn new(object_type: impl Into<String>, object_id: u64) -> Self {
+        Self {
+            object_type: object_type.into(),
+            object_id,
+        }
+    }
+
+    /// Create a reference to a dashboard.
+    pub fn dashboard(id: u64) -> Self {
+        Self::new("dashboard", id)
+    }
+
+    /// Create a reference to a trigger.
+    pub fn trigger(id: u64) -> Self {
+        Self::new("trigger", id)
+    }
+}
+
+/// The properties of a bundle to be created from objects on the appliance.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct NewBundle {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Whether to record which devices and groups each object is assigned to.
+    pub include_assignments: bool,
+    pub objects: Vec<BundleObject>,
+}
+
+/// What to do when an object in a bundle already exists on the target appliance.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Replace the existing object with the one in the bundle.
+    Overwrite,
+    /// Keep the existing object and ignore the one in the bundle. This is the default.
+    #[default]
+    Skip,
+}
+
+/// Options controlling how a bundle is applied.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ApplyOptions {
+    pub policy: ConflictPolicy,
+    /// Whether to restore the device and group assignments recorded in the bundle.
+    pub include_assignments: bool,
+    /// The IDs of connected appliances to which the bundle should also be applied.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub node_ids: Vec<u64>,
+}
+
+/// Get all bundles on the appliance.
+pub async fn list(client: &Client) -> Result<Vec<Bundle>, Error> {
+    client
+        .get("v1/bundles")?
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Create a bundle from objects on the appliance, returning the new bundle's ID.
+pub async fn create(client: &Client, bundle: &NewBundle) -> Result<u64, Error> {
+    let response = client
+        .post("v1/bundles")?
+        .json(bundle)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    created_id(&response)
+}
+
+/// Delete a bundle. Objects created by applying the bundle are not affected.
+pub async fn delete(client: &Client, id: u64) -> Result<(), Error> {
+    client
+        .delete(&format!("v1/bundles/{}", id))?
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+/// Get the contents of a bundle.
+///
+/// The contents are left untyped so that they can be uploaded to another appliance without
+/// losing properties unknown to this crate.
+pub async fn download(client: &Client, id: u64) -> Result<Value, Error> {
+    client
+        .get(&format!("v1/bundles/{}", id))?
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Write the contents of a bundle to a JSON file.
+pub async fn download_to_file(
+    client: &Client,
+    id: u64,
+    path: impl AsRef<Path>,
+) -> Result<(), Error> {
+    let contents = download(client, id).await?;
+    tokio::fs::write(path, serde_json::to_vec_pretty(&contents)?).await?;
+    Ok(())
+}
+
+/// Upload bundle contents obtained by [`download`], returning the new bundle's ID.
+///
+/// Uploading a bundle does not change any customizations until it is applied.
+pub async fn upload(client: &Client, contents: &Value) -> Result<u64, Error> {
+    let response = client
+        .post("v1/bundles")?
+        .json(contents)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    created_id(&response)
+}
+
+/// Upload a bundle file written by [`download_to_file`] or exported from the UI.
+pub async fn upload_file(client: &Client, path: impl AsRef<Path>) -> Result<u64, Error> {
+    let contents: Value = serde_json::from_slice(&tokio::fs::read(path).await?)?;
+    upload(client, &contents).await
+}
+
+/// Start applying a bundle, returning the ID of the job that tracks its progress.
+pub async fn apply(client: &Client, id: u64, options: &ApplyOptions) -> Result<String, Error> {
+    let response = client
+        .post(&format!("v1/bundles/{}/apply", id))?
+        .json(options)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    jobs::job_id(&response)
+}
+
+/// Apply a bundle and wait for the appliance to finish, calling `on_progress` each time the
+/// job status is checked.
+///
+/// Returns an error if the bundle could not be applied or did not finish within the timeout
+/// set in `poll`.
+pub async fn apply_and_wait(
+    client: &Client,
+    id: u64,
+    options: &ApplyOptions,
+    poll: &PollOptions,
+    on_progress: impl FnMut(&Job),
+) -> Result<Job, JobError> {
+    let job_id = apply(client, id, options).await?;
+    client.poll_job(&job_id, poll, on_progress).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ApplyOptions, BundleObject, ConflictPolicy, NewBundle};
+    use serde_json::json;
+
+    #[test]
+    fn serialize_new_bundle() {
+        let bundle = NewBundle {
+            name: "Web Monitoring".into(),
+            objects: vec![BundleObject::dashboard(4), BundleObject::trigger(19)],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            json!({
+                "name": "Web Monitoring",
+                "include_assignments": false,
+                "objects": [
+                    { "object_type": "dashboard", "object_id": 4 },
+                    { "object_type": "trigger", "object_id": 19 }
+                ]
+            }),
+            serde_json::to_value(bundle).unwrap()
+        );
+    }
+
+    #[test]
+    fn serialize_apply_options() {
+        let options = ApplyOptions {
+            policy: ConflictPolicy::Overwrite,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            json!({ "policy": "overwrite", "include_assignments": false }),
+            serde_json::to_value(options).unwrap()
+        );
+    }
+}
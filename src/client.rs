@@ -1,6 +1,7 @@
 //! Clients for calling the ExtraHop REST API, supporting both Reveal(x) 360 and direct appliance
 //! connections.
 
+use crate::jobs::{self, Job, JobError, PollOptions};
 use reqwest::{header, Certificate, Method, RequestBuilder};
 use secstr::SecUtf8;
 use serde::Deserialize;
@@ -175,7 +176,7 @@ impl Saas {
 
         let response = client
             .post(host.join("oauth2/token").expect("OAuth2 path is valid"))
-            .basic_auth(&id, Some(secret.unsecure()))
+            .basic_auth(id, Some(secret.unsecure()))
             .form(&[("grant_type", "client_credentials")])
             .send()
             .await?;
@@ -206,8 +207,10 @@ pub enum ApplianceClientError {
 }
 
 /// Appliance client's server certificate validation behavior.
+#[derive(Default)]
 pub enum CertVerification {
     /// Use the system's standard certificate validation rules and root certificates.
+    #[default]
     System,
     /// Accept any certificate. This is dangerous and should not be done lightly.
     /// Instead, prefer getting the appliance certificate once and creating a client with
@@ -218,15 +221,10 @@ pub enum CertVerification {
     Custom(Certificate),
 }
 
-impl Default for CertVerification {
-    fn default() -> Self {
-        Self::System
-    }
-}
-
 /// A client to communicate with a specific ExtraHop appliance.
 ///
 /// The client holds a connection pool internally, so it is recommended that you create one and reuse it.
+#[derive(Debug)]
 pub struct Appliance {
     root: Url,
     api_key: SecUtf8,
@@ -327,11 +325,7 @@ impl Client {
 
     /// Check if the client is talking to a Reveal(x) 360 tenant.
     pub fn is_saas(&self) -> bool {
-        if let Inner::Saas(_) = self.inner {
-            true
-        } else {
-            false
-        }
+        matches!(self.inner, Inner::Saas(_))
     }
 
     /// Check if the client is talking to a specific ExtraHop appliance.
@@ -371,6 +365,26 @@ impl Client {
 
         Ok(())
     }
+
+    /// Wait for a long-running job to finish, calling `on_progress` each time its status is
+    /// checked.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let response = client.post("v1/bundles/4/apply")?.json(&options).send().await?;
+    /// let job_id = extrahop::jobs::job_id(&response.validate_status().await?)?;
+    /// client
+    ///     .poll_job(&job_id, &Default::default(), |job| println!("{:?}", job.status))
+    ///     .await?;
+    /// ```
+    pub async fn poll_job(
+        &self,
+        id: &str,
+        options: &PollOptions,
+        on_progress: impl FnMut(&Job),
+    ) -> Result<Job, JobError> {
+        jobs::poll(self, id, options, on_progress).await
+    }
 }
 
 impl From<Appliance> for Client {
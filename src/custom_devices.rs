@@ -0,0 +1,411 @@
+//! Typed operations for `/api/v1/customdevices`.
+//!
+//! A custom device aggregates the traffic matching its criteria, such as an on-premise
+//! subnet or the address ranges of a SaaS provider, into a single device.
+//!
+//! # Example
+//! ```rust
+//! use extrahop::custom_devices::{Criterion, NewCustomDevice};
+//!
+//! let device = NewCustomDevice {
+//!     name: "Branch Office".into(),
+//!     criteria: vec![Criterion::new("10.20.0.0/16".parse().unwrap()).dst_ports(443, 443)],
+//!     ..Default::default()
+//! };
+//! # let _ = device;
+//! ```
+
+use crate::api_response::created_id;
+use crate::{ApiResponse, Client, Error};
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// A custom device as returned by the REST API.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct CustomDevice {
+    pub id: u64,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub disabled: bool,
+    /// The identifier shared by the custom device on every appliance.
+    #[serde(default)]
+    pub extrahop_id: Option<String>,
+    #[serde(default)]
+    pub criteria: Vec<Criterion>,
+    /// The time the custom device was last modified, in milliseconds since epoch.
+    #[serde(default)]
+    pub mod_time: Option<u64>,
+}
+
+/// The properties of a custom device to be created.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct NewCustomDevice {
+    pub name: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    pub disabled: bool,
+    /// The identifier to share across appliances. If not set, the appliance generates one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extrahop_id: Option<String>,
+    pub criteria: Vec<Criterion>,
+}
+
+/// Changes to apply to an existing custom device. Fields left as `None` are not changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CustomDeviceUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled: Option<bool>,
+    /// Replacement criteria. When set, all existing criteria are removed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub criteria: Option<Vec<Criterion>>,
+}
+
+/// Traffic which belongs to a custom device.
+///
+/// Port and VLAN bounds are inclusive; an unset bound does not restrict matching traffic.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Criterion {
+    pub ipaddr: Cidr,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub src_port_min: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub src_port_max: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dst_port_min: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dst_port_max: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vlan_min: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vlan_max: Option<u16>,
+}
+
+impl Criterion {
+    /// Create a criterion matching all traffic to or from an address or subnet.
+    pub fn new(ipaddr: Cidr) -> Self {
+        Self {
+            ipaddr,
+            src_port_min: None,
+            src_port_max: None,
+            dst_port_min: None,
+            dst_port_max: None,
+            vlan_min: None,
+            vlan_max: None,
+        }
+    }
+
+    /// Restrict the criterion to traffic from the specified range of source ports.
+    pub fn src_ports(mut self, min: u16, max: u16) -> Self {
+        self.src_port_min = Some(min);
+        self.src_port_max = Some(max);
+        self
+    }
+
+    /// Restrict the criterion to traffic to the specified range of destination ports.
+    pub fn dst_ports(mut self, min: u16, max: u16) -> Self {
+        self.dst_port_min = Some(min);
+        self.dst_port_max = Some(max);
+        self
+    }
+
+    /// Restrict the criterion to traffic on the specified range of VLANs.
+    pub fn vlans(mut self, min: u16, max: u16) -> Self {
+        self.vlan_min = Some(min);
+        self.vlan_max = Some(max);
+        self
+    }
+}
+
+/// An IP address or subnet in CIDR notation, such as `10.0.0.0/8` or `2001:db8::/32`.
+///
+/// Parsing validates the address and prefix length, and rejects subnets with host bits
+/// set (e.g. `10.0.0.1/8`), since those usually indicate a typo. A bare address is treated
+/// as a single-host subnet.
+///
+/// Deserializing is more lenient, since criteria already stored on the appliance may have
+/// host bits set; those bits are cleared, so `10.0.0.1/8` is read as `10.0.0.0/8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    /// Create a subnet from its network address and prefix length.
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Result<Self, CidrError> {
+        let cidr = Self::new_truncating(addr, prefix_len)?;
+        if cidr.addr != addr {
+            return Err(CidrError::HostBitsSet);
+        }
+
+        Ok(cidr)
+    }
+
+    /// Create a subnet from any address within it and its prefix length, clearing the bits
+    /// of `addr` beyond the prefix.
+    pub fn new_truncating(addr: IpAddr, prefix_len: u8) -> Result<Self, CidrError> {
+        let max_len = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+
+        if prefix_len > max_len {
+            return Err(CidrError::PrefixLength(prefix_len));
+        }
+
+        // Left-align the address so the same mask clears the host bits for both families.
+        let bits = match addr {
+            IpAddr::V4(v4) => u128::from(u32::from(v4)) << 96,
+            IpAddr::V6(v6) => u128::from(v6),
+        };
+
+        let mask = u128::MAX
+            .checked_shl(128 - u32::from(prefix_len))
+            .unwrap_or(0);
+        let addr = match addr {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from(((bits & mask) >> 96) as u32)),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(bits & mask)),
+        };
+
+        Ok(Self { addr, prefix_len })
+    }
+
+    /// The network address of the subnet.
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    /// The number of leading bits which identify the network.
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Returns `true` if the subnet contains only one address.
+    pub fn is_host(&self) -> bool {
+        match self.addr {
+            IpAddr::V4(_) => self.prefix_len == 32,
+            IpAddr::V6(_) => self.prefix_len == 128,
+        }
+    }
+}
+
+impl From<IpAddr> for Cidr {
+    fn from(addr: IpAddr) -> Self {
+        let prefix_len = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+
+        Self { addr, prefix_len }
+    }
+}
+
+impl Cidr {
+    /// Parse a subnet, either rejecting host bits with [`Cidr::new`] or clearing them with
+    /// [`Cidr::new_truncating`].
+    fn parse(s: &str, truncate: bool) -> Result<Self, CidrError> {
+        let (addr, prefix_len) = match s.find('/') {
+            Some(idx) => (&s[..idx], Some(&s[idx + 1..])),
+            None => (s, None),
+        };
+
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|_| CidrError::Address(addr.to_string()))?;
+
+        let prefix_len = match prefix_len {
+            None => return Ok(Self::from(addr)),
+            Some(len) => len
+                .parse()
+                .map_err(|_| CidrError::InvalidPrefix(len.to_string()))?,
+        };
+
+        if truncate {
+            Cidr::new_truncating(addr, prefix_len)
+        } else {
+            Cidr::new(addr, prefix_len)
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = CidrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Cidr::parse(s, false)
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_host() {
+            write!(f, "{}", self.addr)
+        } else {
+            write!(f, "{}/{}", self.addr, self.prefix_len)
+        }
+    }
+}
+
+impl Serialize for Cidr {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Cidr {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        Cidr::parse(&String::deserialize(d)?, true).map_err(de::Error::custom)
+    }
+}
+
+/// An error encountered parsing a [`Cidr`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum CidrError {
+    #[error("'{0}' is not a valid IP address")]
+    Address(String),
+    #[error("'{0}' is not a valid prefix length")]
+    InvalidPrefix(String),
+    #[error("Prefix length {0} is too long for the address")]
+    PrefixLength(u8),
+    #[error("Address has bits set beyond the prefix length; use the network address instead")]
+    HostBitsSet,
+}
+
+/// Get all custom devices on the appliance, including their criteria.
+pub async fn list(client: &Client) -> Result<Vec<CustomDevice>, Error> {
+    client
+        .get("v1/customdevices")?
+        .query(&[("include_criteria", true)])
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Get a single custom device by ID, including its criteria.
+pub async fn get(client: &Client, id: u64) -> Result<CustomDevice, Error> {
+    client
+        .get(&format!("v1/customdevices/{}", id))?
+        .query(&[("include_criteria", true)])
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Create a new custom device, returning the ID assigned by the appliance.
+pub async fn create(client: &Client, device: &NewCustomDevice) -> Result<u64, Error> {
+    let response = client
+        .post("v1/customdevices")?
+        .json(device)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    created_id(&response)
+}
+
+/// Apply changes to an existing custom device.
+pub async fn update(client: &Client, id: u64, update: &CustomDeviceUpdate) -> Result<(), Error> {
+    client
+        .patch(&format!("v1/customdevices/{}", id))?
+        .json(update)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+/// Delete a custom device.
+pub async fn delete(client: &Client, id: u64) -> Result<(), Error> {
+    client
+        .delete(&format!("v1/customdevices/{}", id))?
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cidr, CidrError, Criterion};
+    use serde_json::json;
+
+    #[test]
+    fn parse_cidr() {
+        assert_eq!(
+            "10.0.0.0/8",
+            "10.0.0.0/8".parse::<Cidr>().unwrap().to_string()
+        );
+        assert_eq!(
+            "2001:db8::/32",
+            "2001:db8::/32".parse::<Cidr>().unwrap().to_string()
+        );
+        assert_eq!(
+            "10.1.2.3",
+            "10.1.2.3/32".parse::<Cidr>().unwrap().to_string()
+        );
+        assert!("0.0.0.0/0".parse::<Cidr>().is_ok());
+        assert!("10.1.2.3".parse::<Cidr>().unwrap().is_host());
+    }
+
+    #[test]
+    fn reject_invalid_cidr() {
+        assert_eq!(Err(CidrError::HostBitsSet), "10.0.0.1/8".parse::<Cidr>());
+        assert_eq!(
+            Err(CidrError::PrefixLength(33)),
+            "10.0.0.0/33".parse::<Cidr>()
+        );
+        assert_eq!(
+            Err(CidrError::InvalidPrefix("x".into())),
+            "10.0.0.0/x".parse::<Cidr>()
+        );
+        assert_eq!(
+            Err(CidrError::Address("10.0.0".into())),
+            "10.0.0/8".parse::<Cidr>()
+        );
+        assert!(serde_json::from_str::<Cidr>(r#""10.0.0.0/33""#).is_err());
+    }
+
+    #[test]
+    fn deserialize_clears_host_bits() {
+        let cidr: Cidr = serde_json::from_str(r#""10.0.0.1/8""#).unwrap();
+        assert_eq!("10.0.0.0/8", cidr.to_string());
+
+        let cidr: Cidr = serde_json::from_str(r#""2001:db8::1/32""#).unwrap();
+        assert_eq!("2001:db8::/32", cidr.to_string());
+
+        let cidr: Cidr = serde_json::from_str(r#""10.1.2.3/0""#).unwrap();
+        assert_eq!("0.0.0.0/0", cidr.to_string());
+    }
+
+    #[test]
+    fn serialize_criterion() {
+        assert_eq!(
+            json!({ "ipaddr": "192.168.0.0/16", "dst_port_min": 443, "dst_port_max": 443 }),
+            serde_json::to_value(
+                Criterion::new("192.168.0.0/16".parse().unwrap()).dst_ports(443, 443)
+            )
+            .unwrap()
+        );
+    }
+}
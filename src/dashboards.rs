@@ -0,0 +1,309 @@
+//! Typed operations for `/api/v1/dashboards`.
+//!
+//! In addition to listing dashboards, this module supports moving dashboards between users
+//! with [`transfer_ownership`] and between appliances with [`export_to_file`] and
+//! [`import_from_file`].
+
+use crate::api_response::created_id;
+use crate::{ApiResponse, Client, Error};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Properties assigned by the appliance which must not be sent when importing a dashboard.
+const SERVER_ASSIGNED: &[&str] = &["id", "mod_time", "owner", "rights", "short_code"];
+
+/// A dashboard as returned by the REST API.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Dashboard {
+    pub id: u64,
+    pub name: String,
+    /// The username of the dashboard's owner. Built-in dashboards do not have an owner.
+    #[serde(default)]
+    pub owner: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub comment: Option<String>,
+    /// The short code used in dashboard URLs.
+    #[serde(default)]
+    pub short_code: Option<String>,
+    /// The time the dashboard was last modified, in milliseconds since epoch.
+    #[serde(default)]
+    pub mod_time: Option<u64>,
+}
+
+/// The level of access a user or group has to a shared dashboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Access {
+    Viewer,
+    Editor,
+}
+
+/// The users and groups with whom a dashboard is shared.
+///
+/// When used to update sharing, a value of `None` revokes the user or group's access.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sharing {
+    /// The access granted to every user on the appliance.
+    ///
+    /// When used to update sharing, `None` leaves this access unchanged and `Some(None)`
+    /// revokes it.
+    #[serde(
+        deserialize_with = "deserialize_anyone",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub anyone: Option<Option<Access>>,
+    pub users: BTreeMap<String, Option<Access>>,
+    pub groups: BTreeMap<String, Option<Access>>,
+}
+
+/// Read an explicit `null` as `Some(None)`, so a dashboard which is not shared with everyone
+/// keeps that setting when its sharing is sent back to the appliance.
+fn deserialize_anyone<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Option<Access>>, D::Error> {
+    Option::<Access>::deserialize(d).map(Some)
+}
+
+#[derive(Serialize)]
+struct OwnerUpdate<'a> {
+    owner: &'a str,
+}
+
+/// Get all dashboards visible to the caller.
+pub async fn list(client: &Client) -> Result<Vec<Dashboard>, Error> {
+    client
+        .get("v1/dashboards")?
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Get a single dashboard by ID.
+pub async fn get(client: &Client, id: u64) -> Result<Dashboard, Error> {
+    client
+        .get(&format!("v1/dashboards/{}", id))?
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Delete a dashboard.
+pub async fn delete(client: &Client, id: u64) -> Result<(), Error> {
+    client
+        .delete(&format!("v1/dashboards/{}", id))?
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+/// Make `owner` the owner of a dashboard.
+pub async fn set_owner(client: &Client, id: u64, owner: &str) -> Result<(), Error> {
+    client
+        .patch(&format!("v1/dashboards/{}", id))?
+        .json(&OwnerUpdate { owner })
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+/// Transfer every dashboard owned by `from` to `to`.
+///
+/// An error is returned only if the dashboards could not be listed; otherwise, each dashboard
+/// that was owned by `from` is returned with the outcome of its transfer.
+pub async fn transfer_ownership(
+    client: &Client,
+    from: &str,
+    to: &str,
+) -> Result<Vec<(Dashboard, Result<(), Error>)>, Error> {
+    let mut results = vec![];
+    for dashboard in list(client).await? {
+        if dashboard.owner.as_deref() == Some(from) {
+            let result = set_owner(client, dashboard.id, to).await;
+            results.push((dashboard, result));
+        }
+    }
+
+    Ok(results)
+}
+
+/// Get the users and groups with whom a dashboard is shared.
+pub async fn get_sharing(client: &Client, id: u64) -> Result<Sharing, Error> {
+    client
+        .get(&format!("v1/dashboards/{}/sharing", id))?
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Grant or revoke access for the users and groups in `sharing`, leaving others unchanged.
+pub async fn update_sharing(client: &Client, id: u64, sharing: &Sharing) -> Result<(), Error> {
+    client
+        .patch(&format!("v1/dashboards/{}/sharing", id))?
+        .json(sharing)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+/// Replace a dashboard's sharing so that only the users and groups in `sharing` have access.
+pub async fn replace_sharing(client: &Client, id: u64, sharing: &Sharing) -> Result<(), Error> {
+    client
+        .put(&format!("v1/dashboards/{}/sharing", id))?
+        .json(sharing)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+/// Get the full definition of a dashboard.
+///
+/// The definition is left untyped so that properties added in newer firmware survive
+/// being exported and imported.
+pub async fn export(client: &Client, id: u64) -> Result<Value, Error> {
+    client
+        .get(&format!("v1/dashboards/{}", id))?
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Create a dashboard from a definition obtained by [`export`], returning the new ID.
+///
+/// Properties assigned by the source appliance, such as the ID and owner, are removed before
+/// the definition is sent; the new dashboard is owned by the caller.
+pub async fn import(client: &Client, definition: &Value) -> Result<u64, Error> {
+    let response = client
+        .post("v1/dashboards")?
+        .json(&strip_server_assigned(definition))
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    created_id(&response)
+}
+
+/// Export the specified dashboards to a JSON file containing an array of definitions.
+pub async fn export_to_file(
+    client: &Client,
+    ids: &[u64],
+    path: impl AsRef<Path>,
+) -> Result<(), Error> {
+    let mut definitions = Vec::with_capacity(ids.len());
+    for id in ids {
+        definitions.push(export(client, *id).await?);
+    }
+
+    tokio::fs::write(path, serde_json::to_vec_pretty(&definitions)?).await?;
+    Ok(())
+}
+
+/// Import every dashboard in a file written by [`export_to_file`].
+///
+/// An error is returned only if the file could not be read; otherwise, the outcome of each
+/// import is returned in the order the definitions appear in the file.
+pub async fn import_from_file(
+    client: &Client,
+    path: impl AsRef<Path>,
+) -> Result<Vec<Result<u64, Error>>, Error> {
+    let definitions: Vec<Value> = serde_json::from_slice(&tokio::fs::read(path).await?)?;
+
+    let mut results = Vec::with_capacity(definitions.len());
+    for definition in &definitions {
+        results.push(import(client, definition).await);
+    }
+
+    Ok(results)
+}
+
+fn strip_server_assigned(definition: &Value) -> Value {
+    let mut definition = definition.clone();
+    if let Some(properties) = definition.as_object_mut() {
+        for key in SERVER_ASSIGNED {
+            properties.remove(*key);
+        }
+    }
+
+    definition
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{strip_server_assigned, Access, Sharing};
+    use serde_json::json;
+
+    #[test]
+    fn strip_definition() {
+        assert_eq!(
+            json!({ "name": "Web Tier", "params": {} }),
+            strip_server_assigned(&json!({
+                "id": 12,
+                "name": "Web Tier",
+                "owner": "kenp",
+                "short_code": "aB3x",
+                "params": {}
+            }))
+        );
+    }
+
+    #[test]
+    fn serialize_revoked_access() {
+        let mut sharing = Sharing::default();
+        sharing.users.insert("kenp".into(), None);
+        sharing.groups.insert("soc".into(), Some(Access::Editor));
+
+        assert_eq!(
+            json!({ "users": { "kenp": null }, "groups": { "soc": "editor" } }),
+            serde_json::to_value(sharing).unwrap()
+        );
+    }
+
+    #[test]
+    fn anyone_unchanged_by_default() {
+        assert_eq!(
+            json!({ "users": {}, "groups": {} }),
+            serde_json::to_value(Sharing::default()).unwrap()
+        );
+
+        let revoke = Sharing {
+            anyone: Some(None),
+            ..Default::default()
+        };
+        assert_eq!(
+            json!({ "anyone": null, "users": {}, "groups": {} }),
+            serde_json::to_value(revoke).unwrap()
+        );
+    }
+
+    #[test]
+    fn deserialize_anyone() {
+        let sharing: Sharing = serde_json::from_value(json!({ "anyone": null })).unwrap();
+        assert_eq!(Some(None), sharing.anyone);
+
+        let sharing: Sharing = serde_json::from_value(json!({ "anyone": "viewer" })).unwrap();
+        assert_eq!(Some(Some(Access::Viewer)), sharing.anyone);
+
+        let sharing: Sharing = serde_json::from_value(json!({})).unwrap();
+        assert_eq!(None, sharing.anyone);
+    }
+}
@@ -0,0 +1,297 @@
+//! Typed operations for `/api/v1/detections`.
+//!
+//! Detections are searched with a [`DetectionSearch`], which can either be sent as a single
+//! request using [`search`] or followed across pages using [`search_all`].
+
+use crate::oid::Device;
+use crate::{paging, ApiResponse, Client, Error, Oid, QueryTime};
+use futures::stream::Stream;
+use serde::{Deserialize, Serialize};
+
+/// The number of detections requested per page by [`search_all`] when the search does not
+/// set a limit.
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// A detection as returned by the REST API.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Detection {
+    pub id: u64,
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    /// The machine-friendly name of the detection type.
+    #[serde(rename = "type")]
+    pub detection_type: String,
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// The risk of the detection, from 1 to 99.
+    #[serde(default)]
+    pub risk_score: Option<u8>,
+    #[serde(default)]
+    pub participants: Vec<Participant>,
+    /// The time the detection started, in milliseconds since epoch.
+    pub start_time: u64,
+    /// The time the detection ended, in milliseconds since epoch.
+    #[serde(default)]
+    pub end_time: Option<u64>,
+    /// The time the detection was last updated, in milliseconds since epoch.
+    #[serde(default)]
+    pub update_time: Option<u64>,
+    #[serde(default)]
+    pub status: Option<Status>,
+    #[serde(default)]
+    pub assignee: Option<String>,
+    #[serde(default)]
+    pub resolution: Option<Resolution>,
+    #[serde(default)]
+    pub ticket_id: Option<String>,
+}
+
+/// An endpoint involved in a detection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Participant {
+    /// The kind of participant, such as `device` or `ipaddr`.
+    pub object_type: String,
+    /// The ID of the participant, when it is a device.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_id: Option<Oid>,
+    /// The value of the participant, such as an IP address.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<ParticipantRole>,
+}
+
+impl Participant {
+    /// Create a participant for a device.
+    pub fn device(id: impl Into<Oid<Device>>) -> Self {
+        Self {
+            object_type: "device".into(),
+            object_id: Some(id.into().untyped()),
+            object_value: None,
+            role: None,
+        }
+    }
+
+    /// Create a participant for an IP address.
+    pub fn ipaddr(addr: impl Into<String>) -> Self {
+        Self {
+            object_type: "ipaddr".into(),
+            object_id: None,
+            object_value: Some(addr.into()),
+            role: None,
+        }
+    }
+
+    /// Require that the participant fill the specified role in the detection.
+    pub fn with_role(mut self, role: ParticipantRole) -> Self {
+        self.role = Some(role);
+        self
+    }
+}
+
+/// The part an endpoint played in a detection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParticipantRole {
+    Offender,
+    Victim,
+    /// A role not known to this version of the crate, which cannot be sent in requests.
+    #[serde(other, serialize_with = "crate::unknown::reject")]
+    Other,
+}
+
+/// The triage state of a detection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    New,
+    Acknowledged,
+    InProgress,
+    Closed,
+    /// A status not known to this version of the crate, which cannot be sent in requests.
+    #[serde(other, serialize_with = "crate::unknown::reject")]
+    Other,
+}
+
+/// The outcome of a closed detection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Resolution {
+    ActionTaken,
+    NoActionTaken,
+    /// A resolution not known to this version of the crate, which cannot be sent in requests.
+    #[serde(other, serialize_with = "crate::unknown::reject")]
+    Other,
+}
+
+/// Criteria that detections must match to be returned from a search.
+///
+/// Empty lists and `None` values do not restrict the results.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DetectionFilter {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<String>,
+    /// Detection type names, such as `data_exfil`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub types: Vec<String>,
+    /// Only return detections with at least this risk score.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub risk_score_min: Option<u8>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub status: Vec<Status>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub assignee: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub resolution: Vec<Resolution>,
+    /// Only return detections in which all of the specified endpoints participated.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub participants: Vec<Participant>,
+}
+
+/// The body of a `v1/detections/search` request.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DetectionSearch {
+    /// The start of the window in which the detection must have been active.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<QueryTime>,
+    /// The end of the window in which the detection must have been active.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<QueryTime>,
+    pub filter: DetectionFilter,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+}
+
+/// Changes to apply to a detection. Fields left as `None` are not changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DetectionUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<Status>,
+    /// The user to assign the detection to. Use `Some(String::new())` to unassign.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assignee: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<Resolution>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ticket_id: Option<String>,
+}
+
+/// Get a single detection by ID.
+pub async fn get(client: &Client, id: u64) -> Result<Detection, Error> {
+    client
+        .get(&format!("v1/detections/{}", id))?
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Get the detections matching `search`. This makes a single request, so results are
+/// bounded by the search's `limit` and `offset`.
+pub async fn search(client: &Client, search: &DetectionSearch) -> Result<Vec<Detection>, Error> {
+    client
+        .post("v1/detections/search")?
+        .json(search)
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Get all detections matching `search`, requesting additional pages as the stream is
+/// consumed.
+///
+/// The search's `limit` is used as the page size, defaulting to [`DEFAULT_PAGE_SIZE`] when
+/// unset or 0, and its `offset` is used as the starting position. The stream ends after the
+/// first page that is not full, or after the first error.
+pub fn search_all<'a>(
+    client: &'a Client,
+    search: &'a DetectionSearch,
+) -> impl Stream<Item = Result<Detection, Error>> + 'a {
+    paging::pages(
+        search.limit,
+        search.offset,
+        DEFAULT_PAGE_SIZE,
+        move |limit, offset| {
+            let page = DetectionSearch {
+                limit: Some(limit),
+                offset: Some(offset),
+                ..search.clone()
+            };
+
+            async move { self::search(client, &page).await }
+        },
+    )
+}
+
+/// Apply triage changes such as status, assignee, or resolution to a detection.
+pub async fn update(client: &Client, id: u64, update: &DetectionUpdate) -> Result<(), Error> {
+    client
+        .patch(&format!("v1/detections/{}", id))?
+        .json(update)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DetectionFilter, DetectionSearch, Participant, ParticipantRole, Status};
+    use crate::Oid;
+    use serde_json::json;
+
+    #[test]
+    fn serialize_search() {
+        let search = DetectionSearch {
+            from: Some("-1d".into()),
+            filter: DetectionFilter {
+                risk_score_min: Some(60),
+                status: vec![Status::New, Status::InProgress],
+                participants: vec![
+                    Participant::device(Oid::new(12)).with_role(ParticipantRole::Victim)
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            json!({
+                "from": "-1d",
+                "filter": {
+                    "risk_score_min": 60,
+                    "status": ["new", "in_progress"],
+                    "participants": [
+                        { "object_type": "device", "object_id": 12, "role": "victim" }
+                    ]
+                }
+            }),
+            serde_json::to_value(search).unwrap()
+        );
+    }
+
+    #[test]
+    fn deserialize_unknown_status() {
+        assert_eq!(
+            Status::Other,
+            serde_json::from_str::<Status>(r#""escalated""#).unwrap()
+        );
+    }
+
+    #[test]
+    fn unknown_status_not_sent() {
+        let filter = DetectionFilter {
+            status: vec![Status::Other],
+            ..Default::default()
+        };
+        assert!(serde_json::to_value(filter).is_err());
+    }
+}
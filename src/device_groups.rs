@@ -0,0 +1,228 @@
+//! Typed operations for `/api/v1/devicegroups`.
+//!
+//! Device groups are either _static_, in which case membership is managed explicitly
+//! using [`update_members`], or _dynamic_, in which case the appliance computes membership
+//! from the group's filter. Both kinds can be used as activity map sources via
+//! `Source::device_group`.
+
+use crate::api_response::created_id;
+use crate::oid::{self, Device};
+use crate::{ApiResponse, Assignment, Client, Error, Oid};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The largest number of devices that will be sent in a single membership request.
+pub const MEMBERSHIP_BATCH_SIZE: usize = 1000;
+
+/// The number of members requested per page when resolving a group's membership.
+const MEMBERS_PAGE_SIZE: usize = 1000;
+
+/// A device group as returned by the REST API.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct DeviceGroup {
+    pub id: Oid<oid::DeviceGroup>,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Whether the group is defined by the system rather than a user.
+    #[serde(default)]
+    pub built_in: bool,
+    /// Whether membership is computed from `filter` rather than managed explicitly.
+    #[serde(default)]
+    pub dynamic: bool,
+    /// The filter used to compute membership of a dynamic group.
+    #[serde(default)]
+    pub filter: Option<Value>,
+    #[serde(default)]
+    pub include_custom_devices: bool,
+    /// The time the group was last modified, in milliseconds since epoch.
+    #[serde(default)]
+    pub mod_time: Option<u64>,
+}
+
+/// The properties of a device group to be created.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct NewDeviceGroup {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub dynamic: bool,
+    /// The filter for a dynamic group. This should be `None` for static groups.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_custom_devices: Option<bool>,
+}
+
+impl NewDeviceGroup {
+    /// Create the properties for a static group, whose members must be assigned explicitly.
+    pub fn new_static(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Create the properties for a dynamic group whose members match `filter`.
+    pub fn new_dynamic(name: impl Into<String>, filter: Value) -> Self {
+        Self {
+            name: name.into(),
+            dynamic: true,
+            filter: Some(filter),
+            ..Default::default()
+        }
+    }
+}
+
+/// Changes to apply to an existing device group. Fields left as `None` are not changed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DeviceGroupUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dynamic: Option<bool>,
+    /// A replacement filter for a dynamic group.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_custom_devices: Option<bool>,
+}
+
+/// A device as returned by the group membership endpoint; only the ID is retained.
+#[derive(Deserialize)]
+struct Member {
+    id: Oid<Device>,
+}
+
+/// Get all device groups visible to the caller.
+pub async fn list(client: &Client) -> Result<Vec<DeviceGroup>, Error> {
+    client
+        .get("v1/devicegroups")?
+        .query(&[("all", true)])
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Get a single device group by ID.
+pub async fn get(client: &Client, id: &Oid<oid::DeviceGroup>) -> Result<DeviceGroup, Error> {
+    client
+        .get(&format!("v1/devicegroups/{}", id))?
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Create a new device group, returning the ID assigned by the appliance.
+pub async fn create(
+    client: &Client,
+    group: &NewDeviceGroup,
+) -> Result<Oid<oid::DeviceGroup>, Error> {
+    let response = client
+        .post("v1/devicegroups")?
+        .json(group)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    created_id(&response).map(Oid::new)
+}
+
+/// Apply changes to an existing device group.
+pub async fn update(
+    client: &Client,
+    id: &Oid<oid::DeviceGroup>,
+    update: &DeviceGroupUpdate,
+) -> Result<(), Error> {
+    client
+        .patch(&format!("v1/devicegroups/{}", id))?
+        .json(update)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+/// Delete a device group. This does not delete the member devices.
+pub async fn delete(client: &Client, id: &Oid<oid::DeviceGroup>) -> Result<(), Error> {
+    client
+        .delete(&format!("v1/devicegroups/{}", id))?
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+/// Add and remove static members of a device group.
+///
+/// Large changes are sent in batches of at most [`MEMBERSHIP_BATCH_SIZE`] devices. Batches
+/// are sent in order, and the first failure stops any further batches from being sent.
+pub async fn update_members(
+    client: &Client,
+    id: &Oid<oid::DeviceGroup>,
+    change: &Assignment,
+) -> Result<(), Error> {
+    let endpoint = format!("v1/devicegroups/{}/devices", id);
+    for batch in change.batches(MEMBERSHIP_BATCH_SIZE) {
+        client
+            .post(&endpoint)?
+            .json(&batch)
+            .send()
+            .await?
+            .validate_status()
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Get the IDs of the devices which are currently members of a device group.
+///
+/// For dynamic groups, this is the membership as computed by the appliance at the time of
+/// the request.
+pub async fn members(
+    client: &Client,
+    id: &Oid<oid::DeviceGroup>,
+) -> Result<Vec<Oid<Device>>, Error> {
+    let endpoint = format!("v1/devicegroups/{}/devices", id);
+    let mut oids = vec![];
+    loop {
+        let page = client
+            .get(&endpoint)?
+            .query(&[("limit", MEMBERS_PAGE_SIZE), ("offset", oids.len())])
+            .send()
+            .await?
+            .validate_and_read::<Vec<Member>>()
+            .await?;
+
+        let page_len = page.len();
+        oids.extend(page.into_iter().map(|member| member.id));
+        if page_len < MEMBERS_PAGE_SIZE {
+            return Ok(oids);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NewDeviceGroup;
+    use serde_json::json;
+
+    #[test]
+    fn serialize_static_group() {
+        assert_eq!(
+            json!({ "name": "Web Servers", "dynamic": false }),
+            serde_json::to_value(NewDeviceGroup::new_static("Web Servers")).unwrap()
+        );
+    }
+}
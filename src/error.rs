@@ -1,3 +1,4 @@
+use crate::ParseQueryTimeError;
 use reqwest::StatusCode;
 use std::fmt;
 use thiserror::Error;
@@ -7,6 +8,13 @@ use thiserror::Error;
 pub enum Error {
     Reqwest(#[from] reqwest::Error),
     Rest(#[from] RestError),
+    Url(#[from] url::ParseError),
+    Io(#[from] std::io::Error),
+    Json(#[from] serde_json::Error),
+    /// A query time could not be resolved to an absolute time.
+    QueryTime(#[from] ParseQueryTimeError),
+    /// The API reported that an object was created, but did not say where to find it.
+    MissingLocation,
 }
 
 /// An application-level error returned by the REST API.
@@ -39,13 +47,13 @@ impl RestError {
 
     /// Get the message returned by the system, if one is available.
     pub fn message(&self) -> Option<&str> {
-        self.message.as_ref().map(|s| s.as_str())
+        self.message.as_deref()
     }
 }
 
 impl fmt::Display for RestError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.status)?;
+        write!(f, "{}", self.status.as_u16())?;
         if let Some(reason) = self.status.canonical_reason() {
             write!(f, "/{}", reason)?;
         }
@@ -0,0 +1,210 @@
+//! Send the same request to many appliances at once.
+//!
+//! # Example
+//! ```rust,ignore
+//! use extrahop::fleet::{self, Fleet};
+//!
+//! let fleet = Fleet::new(sensors).with_concurrency(8);
+//! let results = fleet
+//!     .post_json::<_, activitymap::Response>("v1/activitymaps/query", &query)
+//!     .await;
+//!
+//! let merged = fleet::merge(results);
+//! for (appliance, error) in &merged.failures {
+//!     eprintln!("{}: {}", appliance, error);
+//! }
+//! ```
+
+use crate::client::Appliance;
+use crate::{ApiResponse, Error};
+use futures::stream::{self, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::future::Future;
+
+/// The number of requests a [`Fleet`] will have in flight at once unless configured otherwise.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// A set of appliances which can be sent the same request concurrently.
+pub struct Fleet {
+    members: Vec<Appliance>,
+    concurrency: usize,
+}
+
+impl Fleet {
+    /// Create a fleet from clients for each appliance.
+    pub fn new(members: Vec<Appliance>) -> Self {
+        Self {
+            members,
+            concurrency: DEFAULT_CONCURRENCY,
+        }
+    }
+
+    /// Set the maximum number of appliances which will be sent requests at once.
+    ///
+    /// # Panics
+    /// This function will panic if `limit` is 0.
+    pub fn with_concurrency(mut self, limit: usize) -> Self {
+        assert!(limit > 0, "Fleet concurrency must be at least 1");
+        self.concurrency = limit;
+        self
+    }
+
+    /// Get the appliances in the fleet.
+    pub fn members(&self) -> &[Appliance] {
+        &self.members
+    }
+
+    /// Call `f` once for each appliance, with at most the fleet's concurrency limit running at
+    /// once.
+    ///
+    /// Results are returned in the same order as [`Fleet::members`], regardless of the order
+    /// in which the appliances respond.
+    pub async fn run<'a, F, Fut, T, E>(&'a self, f: F) -> Vec<FleetResult<'a, T, E>>
+    where
+        F: Fn(&'a Appliance) -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        stream::iter(&self.members)
+            .map(|appliance| {
+                let request = f(appliance);
+                async move {
+                    FleetResult {
+                        appliance,
+                        result: request.await,
+                    }
+                }
+            })
+            .buffered(self.concurrency)
+            .collect()
+            .await
+    }
+
+    /// Make a `GET` request to the same endpoint on every appliance and read each response as
+    /// JSON.
+    pub async fn get_json<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+    ) -> Vec<FleetResult<'_, T, Error>> {
+        self.run(|appliance| async move {
+            appliance
+                .get(endpoint)?
+                .send()
+                .await?
+                .validate_and_read()
+                .await
+        })
+        .await
+    }
+
+    /// Make a `POST` request with the same JSON body to the same endpoint on every appliance,
+    /// and read each response as JSON.
+    pub async fn post_json<B: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        body: &B,
+    ) -> Vec<FleetResult<'_, T, Error>> {
+        self.run(|appliance| async move {
+            appliance
+                .post(endpoint)?
+                .json(body)
+                .send()
+                .await?
+                .validate_and_read()
+                .await
+        })
+        .await
+    }
+}
+
+/// The outcome of a fleet request for a single appliance.
+#[derive(Debug)]
+pub struct FleetResult<'a, T, E = Error> {
+    pub appliance: &'a Appliance,
+    pub result: Result<T, E>,
+}
+
+/// Output which can be combined across appliances.
+pub trait Merge {
+    /// Add the contents of `other` into `self`.
+    fn merge(&mut self, other: Self);
+}
+
+impl<T> Merge for Vec<T> {
+    fn merge(&mut self, other: Self) {
+        self.extend(other);
+    }
+}
+
+/// The successful results of a fleet request combined into one value.
+#[derive(Debug)]
+pub struct Merged<'a, T, E = Error> {
+    /// The combined output, or `None` if no appliance succeeded.
+    pub value: Option<T>,
+    /// The appliances whose requests failed, along with the reason.
+    pub failures: Vec<(&'a Appliance, E)>,
+}
+
+/// Combine the successful results of a fleet request, setting aside any failures.
+pub fn merge<'a, T: Merge, E>(
+    results: impl IntoIterator<Item = FleetResult<'a, T, E>>,
+) -> Merged<'a, T, E> {
+    let mut merged: Merged<'a, T, E> = Merged {
+        value: None,
+        failures: vec![],
+    };
+
+    for FleetResult { appliance, result } in results {
+        match (result, &mut merged.value) {
+            (Ok(value), Some(existing)) => existing.merge(value),
+            (Ok(value), None) => merged.value = Some(value),
+            (Err(e), _) => merged.failures.push((appliance, e)),
+        }
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Fleet;
+    use crate::client::Appliance;
+    use crate::CertVerification;
+
+    fn appliance(host: &str) -> Appliance {
+        Appliance::new(host, "key".into(), CertVerification::System).unwrap()
+    }
+
+    #[tokio::test]
+    async fn run_preserves_order_and_merges() {
+        let fleet =
+            Fleet::new(vec![appliance("a"), appliance("b"), appliance("c")]).with_concurrency(2);
+
+        let results = fleet
+            .run(|appliance| async move {
+                let name = appliance.to_string();
+                if name.starts_with('b') {
+                    Err(name)
+                } else {
+                    Ok(vec![name])
+                }
+            })
+            .await;
+
+        let order: Vec<_> = results.iter().map(|r| r.appliance.to_string()).collect();
+        assert_eq!(
+            vec!["a (Appliance)", "b (Appliance)", "c (Appliance)"],
+            order
+        );
+
+        let merged = super::merge(results);
+        assert_eq!(
+            Some(vec![
+                "a (Appliance)".to_string(),
+                "c (Appliance)".to_string()
+            ]),
+            merged.value
+        );
+        assert_eq!(1, merged.failures.len());
+    }
+}
@@ -0,0 +1,183 @@
+//! Typed access to `/api/v1/jobs`, which reports the progress of long-running operations.
+//!
+//! Operations such as applying a bundle respond with `202 Accepted` and a `Location` header
+//! pointing at a job. Use [`job_id`] to read the job ID from such a response, and then
+//! [`Client::poll_job`] to wait for the job to finish.
+
+use crate::api_response::location_id;
+use crate::{ApiResponse, Client, Error};
+use reqwest::Response;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// The longest time [`PollOptions::default`] waits for a job to finish.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// The state of a long-running operation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Job {
+    pub id: String,
+    /// The kind of operation, such as `bundle_apply`.
+    #[serde(default, rename = "type")]
+    pub job_type: Option<String>,
+    pub status: JobStatus,
+    /// The 1-based index of the step currently being performed.
+    #[serde(default)]
+    pub step_number: Option<u32>,
+    #[serde(default)]
+    pub total_steps: Option<u32>,
+    /// A human-friendly description of the current step.
+    #[serde(default)]
+    pub step_description: Option<String>,
+}
+
+/// The lifecycle stage of a job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum JobStatus {
+    Created,
+    Running,
+    Done,
+    Failed,
+    /// A status not known to this version of the crate. Polling treats this as still running,
+    /// so a job that stops in such a status is reported as timed out.
+    #[serde(other)]
+    Other,
+}
+
+impl JobStatus {
+    /// Returns `true` if the job has stopped, either because it succeeded or failed.
+    pub fn is_finished(self) -> bool {
+        match self {
+            JobStatus::Done | JobStatus::Failed => true,
+            JobStatus::Created | JobStatus::Running | JobStatus::Other => false,
+        }
+    }
+}
+
+/// Controls how often and for how long a job is polled.
+///
+/// By default, the job is checked every second for up to [`DEFAULT_TIMEOUT`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollOptions {
+    /// The time to wait between checks of the job's status.
+    pub interval: Duration,
+    /// The longest time to wait for the job to finish. If `None`, polling continues until the
+    /// job finishes or a request fails.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for PollOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            timeout: Some(DEFAULT_TIMEOUT),
+        }
+    }
+}
+
+/// An error encountered while waiting for a job to finish.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum JobError {
+    /// The appliance reported that the job failed.
+    #[error("Job {} failed{}", .0.id, describe_step(.0))]
+    Failed(Job),
+    /// The job had not finished when the timeout elapsed; the job may still be running.
+    #[error("Timed out waiting for job {}{}", .0.id, describe_step(.0))]
+    TimedOut(Job),
+    /// The job's status could not be retrieved.
+    #[error("Unable to get job status")]
+    Client(#[from] Error),
+}
+
+impl JobError {
+    /// Get the last known state of the job, if it was retrieved.
+    pub fn job(&self) -> Option<&Job> {
+        match self {
+            JobError::Failed(job) | JobError::TimedOut(job) => Some(job),
+            JobError::Client(_) => None,
+        }
+    }
+}
+
+fn describe_step(job: &Job) -> String {
+    job.step_description
+        .as_ref()
+        .map(|step| format!(" during '{}'", step))
+        .unwrap_or_default()
+}
+
+/// Get the ID of the job created by an operation from its `Location` header.
+pub fn job_id(response: &Response) -> Result<String, Error> {
+    location_id(response).map(String::from)
+}
+
+/// Get the current state of a job.
+pub async fn get(client: &Client, id: &str) -> Result<Job, Error> {
+    client
+        .get(&format!("v1/jobs/{}", id))?
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Check the state of a job until it finishes, calling `on_progress` after each check.
+///
+/// Returns the final state of the job if it succeeded. See also [`Client::poll_job`].
+pub async fn poll(
+    client: &Client,
+    id: &str,
+    options: &PollOptions,
+    mut on_progress: impl FnMut(&Job),
+) -> Result<Job, JobError> {
+    let start = Instant::now();
+    loop {
+        let job = get(client, id).await?;
+        on_progress(&job);
+
+        match job.status {
+            JobStatus::Done => return Ok(job),
+            JobStatus::Failed => return Err(JobError::Failed(job)),
+            JobStatus::Created | JobStatus::Running | JobStatus::Other => {}
+        }
+
+        if let Some(timeout) = options.timeout {
+            if start.elapsed() + options.interval > timeout {
+                return Err(JobError::TimedOut(job));
+            }
+        }
+
+        tokio::time::sleep(options.interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Job, JobError, JobStatus, PollOptions, DEFAULT_TIMEOUT};
+    use serde_json::json;
+
+    #[test]
+    fn default_timeout_is_finite() {
+        assert_eq!(Some(DEFAULT_TIMEOUT), PollOptions::default().timeout);
+    }
+
+    #[test]
+    fn failed_job_message() {
+        let job: Job = serde_json::from_value(json!({
+            "id": "5cbd7ad2",
+            "status": "FAILED",
+            "step_description": "Applying triggers"
+        }))
+        .unwrap();
+
+        assert_eq!(JobStatus::Failed, job.status);
+        assert_eq!(
+            "Job 5cbd7ad2 failed during 'Applying triggers'",
+            JobError::Failed(job).to_string()
+        );
+    }
+}
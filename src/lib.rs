@@ -11,18 +11,42 @@
 //! public certificate from `http://{EXTRAHOP_HOST}/public.cer` and trust it at the system
 //! level.
 
+pub mod alerts;
+pub mod api_keys;
 mod api_response;
+pub mod appliances;
+mod assignment;
+pub mod audit_log;
+pub mod bundles;
 pub mod client;
+pub mod custom_devices;
+pub mod dashboards;
+pub mod detections;
+pub mod device_groups;
 mod error;
-mod oid;
+pub mod fleet;
+pub mod jobs;
+pub mod oid;
+pub mod packets;
+mod paging;
+mod path;
 mod query_time;
+pub mod running_config;
+pub mod sync;
+pub mod tags;
+pub mod threat_collections;
+pub mod time_slices;
+pub mod triggers;
+mod unknown;
+pub mod users;
 
 #[cfg(feature = "topology")]
 pub mod activitymap;
 
 pub use api_response::ApiResponse;
+pub use assignment::Assignment;
 #[doc(inline)]
 pub use client::{CertVerification, Client};
 pub use error::{Error, RestError};
 pub use oid::Oid;
-pub use query_time::QueryTime;
+pub use query_time::{ParseQueryTimeError, QueryRange, QueryTime};
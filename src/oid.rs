@@ -1,22 +1,258 @@
-use serde::{Deserialize, Serialize};
+//! Metric source IDs, optionally tagged with the kind of object they identify.
+//!
+//! Devices, device groups, and other metric sources are all identified by a number, so an
+//! untyped [`Oid`] can't stop a device group ID from being passed where a device ID is
+//! expected. Functions which only accept one kind of object take an `Oid<Kind>` instead,
+//! such as `Oid<Device>`; typed IDs convert to untyped ones with `From`, while going the
+//! other way requires an explicit [`Oid::cast`]. Raw numbers only convert to untyped IDs, so
+//! a typed ID must be created with [`Oid::new`].
+//!
+//! ```rust
+//! use extrahop::oid::{Device, Oid};
+//!
+//! let device = Oid::<Device>::new(15);
+//! let untyped: Oid = device.into();
+//! assert_eq!(device, untyped.cast::<Device>());
+//! ```
+
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+/// An ID which may identify any kind of object. This is the default for [`Oid`].
+#[derive(Debug)]
+pub enum Untyped {}
+
+/// An individual endpoint.
+#[derive(Debug)]
+pub enum Device {}
+
+/// A user-defined set of devices.
+#[derive(Debug)]
+pub enum DeviceGroup {}
+
+/// A system-defined set of all devices speaking a given protocol.
+#[derive(Debug)]
+pub enum ActivityGroup {}
+
+/// A set of transactions grouped by a trigger or by the appliance.
+#[derive(Debug)]
+pub enum Application {}
+
+/// A capture interface or flow network.
+#[derive(Debug)]
+pub enum Network {}
 
 /// A metric source ID.
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-pub struct Oid(u64);
+///
+/// The type parameter records the kind of object the ID refers to; it is not sent to or
+/// checked by the appliance.
+///
+/// Only untyped IDs can be created from a raw number with `From`:
+///
+/// ```rust,compile_fail
+/// use extrahop::oid::{Device, Oid};
+///
+/// let device: Oid<Device> = 15.into();
+/// ```
+///
+/// OIDs are serialized as numbers, but can be deserialized from either numbers or numeric
+/// strings, since some endpoints return IDs as strings.
+pub struct Oid<K = Untyped> {
+    id: u64,
+    kind: PhantomData<fn() -> K>,
+}
 
-impl Oid {
-    /// Creates a new OID.
+impl<K> Oid<K> {
+    /// Creates a new OID for an object of kind `K`.
     pub fn new(id: u64) -> Self {
-        Oid(id)
+        Oid {
+            id,
+            kind: PhantomData,
+        }
+    }
+
+    /// Gets the numeric value of the OID.
+    pub fn get(self) -> u64 {
+        self.id
     }
+
     /// Gets a representation of the OID for use in a URL path.
     pub fn as_url_part(&self) -> String {
-        format!("{}", self.0)
+        self.to_string()
+    }
+
+    /// Discard the kind of object this ID refers to.
+    pub fn untyped(self) -> Oid {
+        self.cast()
+    }
+
+    /// Treat the ID as referring to a different kind of object.
+    ///
+    /// Nothing checks that the object is of kind `T`, so this should only be used when that
+    /// is known from elsewhere, such as the endpoint which returned the ID.
+    pub fn cast<T>(self) -> Oid<T> {
+        Oid::new(self.id)
     }
 }
 
 impl From<u64> for Oid {
-    fn from(val: u64) -> Self {
-        Oid::new(val)
+    fn from(id: u64) -> Self {
+        Oid::new(id)
+    }
+}
+
+macro_rules! untyped_from {
+    ($($kind:ty),*) => {
+        $(
+            impl From<Oid<$kind>> for Oid {
+                fn from(oid: Oid<$kind>) -> Self {
+                    oid.untyped()
+                }
+            }
+        )*
+    };
+}
+
+untyped_from!(Device, DeviceGroup, ActivityGroup, Application, Network);
+
+// These are implemented by hand because deriving them would require `K` to implement them,
+// and the kinds are never instantiated.
+
+impl<K> fmt::Debug for Oid<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Oid").field(&self.id).finish()
+    }
+}
+
+impl<K> fmt::Display for Oid<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.id.fmt(f)
+    }
+}
+
+impl<K> FromStr for Oid<K> {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<u64>().map(Oid::new)
+    }
+}
+
+impl<K> Clone for Oid<K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K> Copy for Oid<K> {}
+
+impl<K> PartialEq for Oid<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<K> Eq for Oid<K> {}
+
+impl<K> PartialOrd for Oid<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K> Ord for Oid<K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.id.cmp(&other.id)
+    }
+}
+
+impl<K> Hash for Oid<K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl<K> Serialize for Oid<K> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        self.id.serialize(s)
+    }
+}
+
+impl<'de, K> Deserialize<'de> for Oid<K> {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        d.deserialize_any(OidVisitor(PhantomData))
+    }
+}
+
+struct OidVisitor<K>(PhantomData<fn() -> K>);
+
+impl<'de, K> Visitor<'de> for OidVisitor<K> {
+    type Value = Oid<K>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a non-negative integer or numeric string")
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Oid::new(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        if v < 0 {
+            Err(E::invalid_value(de::Unexpected::Signed(v), &self))
+        } else {
+            Ok(Oid::new(v as u64))
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        v.parse()
+            .map_err(|_| E::invalid_value(de::Unexpected::Str(v), &self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Device, DeviceGroup, Oid};
+
+    #[test]
+    fn serde_matches_untyped() {
+        let device: Oid<Device> = serde_json::from_str("15").unwrap();
+        assert_eq!("15", serde_json::to_string(&device).unwrap());
+        assert_eq!(Oid::new(15), device.untyped());
+    }
+
+    #[test]
+    fn deserialize_string() {
+        let oids: Vec<Oid> = serde_json::from_str(r#"[1, "2"]"#).unwrap();
+        assert_eq!(vec![Oid::new(1), Oid::new(2)], oids);
+        assert!(serde_json::from_str::<Oid>(r#""two""#).is_err());
+        assert!(serde_json::from_str::<Oid>("-1").is_err());
+    }
+
+    #[test]
+    fn display_and_parse() {
+        let oid: Oid = "42".parse().unwrap();
+        assert_eq!(42, oid.get());
+        assert_eq!("42", oid.to_string());
+        assert!("4x".parse::<Oid>().is_err());
+
+        let mut oids: Vec<Oid> = vec![Oid::new(10), Oid::new(2), Oid::new(7)];
+        oids.sort();
+        assert_eq!(vec![Oid::new(2), Oid::new(7), Oid::new(10)], oids);
+    }
+
+    #[test]
+    fn cast() {
+        let untyped = Oid::from(3);
+        let group: Oid<DeviceGroup> = untyped.cast();
+        assert_eq!("3", group.as_url_part());
+        assert_eq!(untyped, group.untyped());
     }
 }
@@ -0,0 +1,179 @@
+//! Typed access to `/api/v1/packets/search`.
+//!
+//! Packet captures can be very large, so [`download`] writes the response body to the caller's
+//! writer as it arrives rather than buffering it in memory.
+//!
+//! # Example
+//! ```rust,ignore
+//! use extrahop::packets::{self, PacketSearch};
+//!
+//! let search = PacketSearch {
+//!     from: "-30m".into(),
+//!     ip1: Some("10.0.0.5".parse()?),
+//!     port2: Some(443),
+//!     limit_bytes: Some(100 * 1024 * 1024),
+//!     ..Default::default()
+//! };
+//!
+//! let mut file = tokio::fs::File::create("capture.pcap").await?;
+//! let result = packets::download(&client, &search, &mut file).await?;
+//! if result.may_be_truncated {
+//!     println!("Capture may have stopped at the byte limit; {} bytes written", result.bytes_written);
+//! }
+//! ```
+
+use crate::time_slices::Windowed;
+use crate::{ApiResponse, Client, Error, QueryTime};
+use serde::{Serialize, Serializer};
+use std::net::IpAddr;
+use std::time::Duration;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// The file format of the returned packets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PacketFormat {
+    /// The classic libpcap format. This is the default.
+    #[default]
+    Pcap,
+    Pcapng,
+}
+
+/// The parameters of a packet search.
+///
+/// The optional `ip1`/`port1` and `ip2`/`port2` pairs describe the two ends of a conversation;
+/// packets match if they travel in either direction between the two.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PacketSearch {
+    pub output: PacketFormat,
+    /// The start of the window in which to search for packets.
+    pub from: QueryTime,
+    /// The end of the window in which to search for packets. If not set, defaults to the
+    /// current packet time of the appliance.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<QueryTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip1: Option<IpAddr>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port1: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip2: Option<IpAddr>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port2: Option<u16>,
+    /// A Berkeley Packet Filter expression, such as `tcp port 80`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bpf: Option<String>,
+    /// The largest capture the appliance should return, in bytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit_bytes: Option<u64>,
+    /// The longest time the appliance should spend searching for packets.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_millis"
+    )]
+    pub limit_search_duration: Option<Duration>,
+}
+
+/// A search without an `until` ends at the appliance's current packet time, so it is
+/// treated as ending "now".
+impl Windowed for PacketSearch {
+    fn window(&self) -> (QueryTime, QueryTime) {
+        (self.from.clone(), self.until.clone().unwrap_or_default())
+    }
+
+    fn with_window(&self, from: QueryTime, until: QueryTime) -> Self {
+        PacketSearch {
+            from,
+            until: Some(until),
+            ..self.clone()
+        }
+    }
+}
+
+/// The outcome of a completed packet download.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketDownload {
+    /// The number of bytes written to the caller's writer.
+    pub bytes_written: u64,
+    /// An estimate of whether the capture reached the search's `limit_bytes`, meaning
+    /// matching packets may have been left out.
+    ///
+    /// The appliance does not report whether it stopped early, so this is inferred from the
+    /// number of bytes written reaching the limit. The capture's file and packet headers are
+    /// included in that count, so the estimate can be wrong in either direction when the
+    /// capture is close to the limit.
+    pub may_be_truncated: bool,
+}
+
+/// Search for packets and write the resulting capture file to `writer`.
+///
+/// The body is written as it is received, so memory use does not grow with the size of the
+/// capture. The writer is flushed, but not shut down, before this function returns.
+pub async fn download<W>(
+    client: &Client,
+    search: &PacketSearch,
+    writer: &mut W,
+) -> Result<PacketDownload, Error>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    let mut response = client
+        .post("v1/packets/search")?
+        .json(search)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    let mut bytes_written = 0;
+    while let Some(chunk) = response.chunk().await? {
+        writer.write_all(&chunk).await?;
+        bytes_written += chunk.len() as u64;
+    }
+
+    writer.flush().await?;
+
+    Ok(PacketDownload {
+        bytes_written,
+        may_be_truncated: search
+            .limit_bytes
+            .map(|limit| bytes_written >= limit)
+            .unwrap_or(false),
+    })
+}
+
+fn serialize_millis<S: Serializer>(duration: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+    duration.map(|d| d.as_millis() as u64).serialize(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PacketFormat, PacketSearch};
+    use serde_json::json;
+    use std::time::Duration;
+
+    #[test]
+    fn serialize_search() {
+        let search = PacketSearch {
+            output: PacketFormat::Pcapng,
+            from: (-60_000i64).into(),
+            ip1: Some("10.0.0.5".parse().unwrap()),
+            port2: Some(443),
+            bpf: Some("tcp".into()),
+            limit_search_duration: Some(Duration::from_secs(30)),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            json!({
+                "output": "pcapng",
+                "from": -60000,
+                "ip1": "10.0.0.5",
+                "port2": 443,
+                "bpf": "tcp",
+                "limit_search_duration": 30000
+            }),
+            serde_json::to_value(search).unwrap()
+        );
+    }
+}
@@ -0,0 +1,73 @@
+//! Following endpoints which page their results with `limit` and `offset`.
+
+use crate::Error;
+use futures::stream::{self, Stream, TryStreamExt};
+use std::future::Future;
+
+/// Request pages with `get_page(limit, offset)` as the stream is consumed, yielding each item.
+///
+/// `limit` is used as the page size, defaulting to `default_size`, and `offset` is used as the
+/// starting position. A limit of 0 is treated as unset, since a page of 0 items is never
+/// short and so would never end the stream. The stream ends after the first page that is not
+/// full, or after the first error.
+pub(crate) fn pages<'a, T, F, Fut>(
+    limit: Option<usize>,
+    offset: Option<usize>,
+    default_size: usize,
+    get_page: F,
+) -> impl Stream<Item = Result<T, Error>> + 'a
+where
+    T: 'a,
+    F: Fn(usize, usize) -> Fut + 'a,
+    Fut: Future<Output = Result<Vec<T>, Error>> + 'a,
+{
+    let page_size = match limit {
+        Some(0) | None => default_size,
+        Some(limit) => limit,
+    };
+
+    stream::try_unfold(Some(offset.unwrap_or(0)), move |offset| {
+        let request = offset.map(|offset| (offset, get_page(page_size, offset)));
+        async move {
+            let (offset, request) = match request {
+                Some(request) => request,
+                None => return Ok::<_, Error>(None),
+            };
+
+            let page = request.await?;
+            let next = if page.len() < page_size {
+                None
+            } else {
+                Some(offset + page.len())
+            };
+
+            Ok(Some((stream::iter(page.into_iter().map(Ok)), next)))
+        }
+    })
+    .try_flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::pages;
+    use futures::TryStreamExt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn zero_limit_uses_default() {
+        let items: Vec<usize> = (0..5).collect();
+        let requests = AtomicUsize::new(0);
+
+        let fetched: Vec<usize> = pages(Some(0), Some(1), 2, |limit, offset| {
+            requests.fetch_add(1, Ordering::SeqCst);
+            let page = items.iter().copied().skip(offset).take(limit).collect();
+            async move { Ok(page) }
+        })
+        .try_collect()
+        .await
+        .unwrap();
+
+        assert_eq!(vec![1, 2, 3, 4], fetched);
+        assert_eq!(3, requests.load(Ordering::SeqCst));
+    }
+}
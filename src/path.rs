@@ -0,0 +1,41 @@
+//! Building endpoint paths from user-supplied values.
+
+use std::fmt::Write;
+
+/// Percent-encode a value for use as a single segment of an endpoint path, so characters
+/// such as `/`, `?`, `#`, and `%` are not treated as part of the URL's structure.
+pub(crate) fn segment(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => write!(encoded, "%{:02X}", byte).expect("Writing to a String cannot fail"),
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::segment;
+
+    #[test]
+    fn encode_reserved() {
+        assert_eq!("jdoe", segment("jdoe"));
+        assert_eq!("a%2Fb%3Fc%23d%25e%20f", segment("a/b?c#d%e f"));
+        assert_eq!("j%C3%B6rg", segment("jörg"));
+    }
+
+    #[test]
+    fn survives_join() {
+        let root = url::Url::parse("https://eda.local/api/").unwrap();
+        let url = root
+            .join(&format!("v1/users/{}", segment("a/b?c")))
+            .unwrap();
+        assert_eq!("/api/v1/users/a%2Fb%3Fc", url.path());
+        assert_eq!(None, url.query());
+    }
+}
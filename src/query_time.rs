@@ -1,5 +1,20 @@
-use serde::{Serialize, Serializer};
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::{fmt, num::NonZeroU64};
+use thiserror::Error;
+
+/// The units accepted in relative query times, and their length in milliseconds.
+const UNITS: &[(&str, u64)] = &[
+    ("ms", 1),
+    ("s", 1_000),
+    ("m", 60_000),
+    ("h", 3_600_000),
+    ("d", 86_400_000),
+    ("w", 604_800_000),
+    ("y", 31_536_000_000),
+];
 
 #[derive(Debug, Clone)]
 enum Inner {
@@ -17,7 +32,7 @@ impl Serialize for Inner {
         match self {
             Inner::Now => 0.serialize(serializer),
             Inner::Timestamp(ts) => ts.serialize(serializer),
-            Inner::MsAgo(ms) => ((ms.get() as i64) * -1).serialize(serializer),
+            Inner::MsAgo(ms) => (-(ms.get() as i64)).serialize(serializer),
             Inner::RelativeUnits(string) => string.serialize(serializer),
         }
     }
@@ -39,6 +54,16 @@ impl Serialize for Inner {
 /// let _time: QueryTime = (-30000i64).into();
 /// let _other: QueryTime = "-30m".into();
 /// ```
+///
+/// Parsing a string with [`FromStr`] validates it, so untrusted input such as a config file
+/// should be parsed rather than converted:
+///
+/// ```rust
+/// # use extrahop::QueryTime;
+/// let time: QueryTime = "-2h30m".parse().unwrap();
+/// assert!(time.is_relative());
+/// assert!("-30 minutes".parse::<QueryTime>().is_err());
+/// ```
 #[derive(Clone, Serialize)]
 pub struct QueryTime(Inner);
 
@@ -48,6 +73,18 @@ impl fmt::Debug for QueryTime {
     }
 }
 
+/// Formats the query time as it is sent to the appliance, which [`FromStr`] accepts.
+impl fmt::Display for QueryTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.0 {
+            Inner::Now => f.write_str("0"),
+            Inner::Timestamp(ts) => write!(f, "{}", ts),
+            Inner::MsAgo(ms) => write!(f, "-{}", ms),
+            Inner::RelativeUnits(units) => f.write_str(units),
+        }
+    }
+}
+
 impl QueryTime {
     /// Returns `true` if the query time is relative to the appliance's "now".
     ///
@@ -68,6 +105,185 @@ impl QueryTime {
     pub fn is_absolute(&self) -> bool {
         !self.is_relative()
     }
+
+    /// Get the absolute time this refers to, or `None` if the query time is relative.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use extrahop::QueryTime;
+    /// use std::time::{Duration, SystemTime};
+    ///
+    /// let time = SystemTime::now() - Duration::from_secs(60);
+    /// let query_time = QueryTime::from(time);
+    /// assert!(query_time.to_system_time().is_some());
+    /// assert_eq!(None, QueryTime::from(Duration::from_secs(60)).to_system_time());
+    /// ```
+    pub fn to_system_time(&self) -> Option<SystemTime> {
+        match self.0 {
+            Inner::Timestamp(ts) => Some(UNIX_EPOCH + Duration::from_millis(ts.get())),
+            Inner::Now | Inner::MsAgo(_) | Inner::RelativeUnits(_) => None,
+        }
+    }
+
+    /// Create an absolute query time from milliseconds since epoch. Times at or before the
+    /// epoch are clamped to 1ms after it, since `0` means "now".
+    fn from_epoch_millis(ms: i128) -> Self {
+        let ms = ms.clamp(1, i128::from(u64::MAX)) as u64;
+        Self(Inner::Timestamp(NonZeroU64::new(ms).unwrap()))
+    }
+
+    /// Create a relative query time from a number of milliseconds before "now".
+    fn from_millis_ago(ms: u128) -> Self {
+        match NonZeroU64::new(ms.min(i64::MAX as u128) as u64) {
+            Some(ms) => Self(Inner::MsAgo(ms)),
+            None => Self(Inner::Now),
+        }
+    }
+
+    /// Get the time in milliseconds since epoch, resolving relative times against `now`.
+    ///
+    /// `now` should be the appliance's notion of the current time, such as the `until` of a
+    /// response to a query which ended at "now". Relative times reaching back before the epoch
+    /// resolve to `0`.
+    ///
+    /// # Errors
+    /// Returns an error if the query time holds units that are not valid, which can only
+    /// happen if it was created from a string without being parsed.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use extrahop::QueryTime;
+    /// let now = 1_600_000_000_000;
+    /// assert_eq!(Ok(now - 1_800_000), QueryTime::from("-30m").resolve(now));
+    /// assert_eq!(Ok(now), QueryTime::default().resolve(now));
+    /// assert_eq!(Ok(1_500_000_000_000), QueryTime::from(1_500_000_000_000u64).resolve(now));
+    /// ```
+    pub fn resolve(&self, now: u64) -> Result<u64, ParseQueryTimeError> {
+        Ok(match &self.0 {
+            Inner::Timestamp(ts) => ts.get(),
+            Inner::Now => now,
+            Inner::MsAgo(ms) => now.saturating_sub(ms.get()),
+            Inner::RelativeUnits(units) => now.saturating_sub(relative_units_ms(units)?),
+        })
+    }
+}
+
+/// The local system's current time, in milliseconds since epoch.
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_millis() as u64)
+}
+
+/// A window of time in absolute milliseconds since epoch, including `from` but not `until`.
+///
+/// A range is usually built by resolving the `from` and `until` of a query once the
+/// appliance's current time is known.
+///
+/// # Examples
+/// ```rust
+/// # use extrahop::{QueryRange, QueryTime};
+/// let range = QueryRange::resolve(&"-1h".into(), &QueryTime::default(), 1_600_000_000_000).unwrap();
+/// assert_eq!(std::time::Duration::from_secs(3600), range.duration());
+/// assert!(range.contains(1_600_000_000_000 - 1));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct QueryRange {
+    from: u64,
+    until: u64,
+}
+
+impl QueryRange {
+    /// Create a range between two times. If `until` is before `from`, the range is empty.
+    pub fn new(from: u64, until: u64) -> Self {
+        Self {
+            from,
+            until: until.max(from),
+        }
+    }
+
+    /// Create a range between two times, failing if `until` is before `from`.
+    pub(crate) fn ordered(from: u64, until: u64) -> Result<Self, ParseQueryTimeError> {
+        if until < from {
+            return Err(ParseQueryTimeError::FromAfterUntil { from, until });
+        }
+
+        Ok(Self { from, until })
+    }
+
+    /// Create a range by resolving a query's `from` and `until` against `now`.
+    ///
+    /// See [`QueryTime::resolve`] for how relative times are handled. Returns an error if
+    /// `until` resolves to a time before `from`.
+    pub fn resolve(
+        from: &QueryTime,
+        until: &QueryTime,
+        now: u64,
+    ) -> Result<Self, ParseQueryTimeError> {
+        Self::ordered(from.resolve(now)?, until.resolve(now)?)
+    }
+
+    /// The start of the range, in milliseconds since epoch.
+    pub fn from(&self) -> u64 {
+        self.from
+    }
+
+    /// The end of the range, in milliseconds since epoch.
+    pub fn until(&self) -> u64 {
+        self.until
+    }
+
+    /// The length of time covered by the range.
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.until - self.from)
+    }
+
+    /// Returns `true` if the range covers no time.
+    pub fn is_empty(&self) -> bool {
+        self.from == self.until
+    }
+
+    /// Returns `true` if `time`, in milliseconds since epoch, falls within the range.
+    pub fn contains(&self, time: u64) -> bool {
+        self.from <= time && time < self.until
+    }
+
+    /// Returns `true` if all of `other` falls within the range.
+    pub fn contains_range(&self, other: &QueryRange) -> bool {
+        self.from <= other.from && other.until <= self.until
+    }
+
+    /// Returns `true` if the two ranges share any time.
+    pub fn overlaps(&self, other: &QueryRange) -> bool {
+        self.from < other.until && other.from < self.until
+    }
+
+    /// Split the range into consecutive slices of `size`, in order. The last slice is shorter
+    /// if the range is not a whole number of slices long. Precision below one millisecond is
+    /// discarded.
+    ///
+    /// # Panics
+    /// This function will panic if `size` is less than one millisecond.
+    pub fn split(&self, size: Duration) -> Vec<QueryRange> {
+        let size = size.as_millis().min(u128::from(u64::MAX)) as u64;
+        assert!(size > 0, "Slice size must be at least 1ms");
+
+        let mut slices = vec![];
+        let mut from = self.from;
+        while from < self.until {
+            let until = from.saturating_add(size).min(self.until);
+            slices.push(QueryRange { from, until });
+            from = until;
+        }
+
+        slices
+    }
+}
+
+impl From<QueryRange> for (QueryTime, QueryTime) {
+    fn from(range: QueryRange) -> Self {
+        (range.from.into(), range.until.into())
+    }
 }
 
 impl Default for QueryTime {
@@ -91,13 +307,78 @@ impl From<i64> for QueryTime {
         match val {
             0 => Self(Inner::Now),
             x if x > 0 => Self(Inner::Timestamp(NonZeroU64::new(x as u64).unwrap())),
-            x if x < 0 => Self(Inner::MsAgo(NonZeroU64::new(x.abs() as u64).unwrap())),
+            x if x < 0 => Self(Inner::MsAgo(NonZeroU64::new(x.unsigned_abs()).unwrap())),
             _ => unreachable!(),
         }
     }
 }
 
-impl<'a> From<&'a str> for QueryTime {
+/// Create an absolute query time. Times at or before the Unix epoch are clamped to 1ms
+/// after it, since `0` means "now".
+impl From<SystemTime> for QueryTime {
+    fn from(val: SystemTime) -> Self {
+        match val.duration_since(UNIX_EPOCH) {
+            Ok(since_epoch) => Self::from_epoch_millis(since_epoch.as_millis() as i128),
+            Err(_) => Self::from_epoch_millis(0),
+        }
+    }
+}
+
+/// Create a relative query time the specified duration before "now". Precision below one
+/// millisecond is discarded.
+///
+/// ```rust
+/// # use extrahop::QueryTime;
+/// use std::time::Duration;
+///
+/// assert_eq!(
+///     "-1800000",
+///     serde_json::to_string(&QueryTime::from(Duration::from_secs(30 * 60))).unwrap()
+/// );
+/// ```
+impl From<Duration> for QueryTime {
+    fn from(val: Duration) -> Self {
+        Self::from_millis_ago(val.as_millis())
+    }
+}
+
+/// Create an absolute query time. Times at or before the Unix epoch are clamped to 1ms
+/// after it, since `0` means "now".
+#[cfg(feature = "chrono")]
+impl<Tz: chrono::TimeZone> From<chrono::DateTime<Tz>> for QueryTime {
+    fn from(val: chrono::DateTime<Tz>) -> Self {
+        Self::from_epoch_millis(i128::from(val.timestamp_millis()))
+    }
+}
+
+/// Create a relative query time the specified duration before "now". The sign of the
+/// duration is ignored.
+#[cfg(feature = "chrono")]
+impl From<chrono::Duration> for QueryTime {
+    fn from(val: chrono::Duration) -> Self {
+        Self::from_millis_ago(u128::from(val.num_milliseconds().unsigned_abs()))
+    }
+}
+
+/// Create an absolute query time. Times at or before the Unix epoch are clamped to 1ms
+/// after it, since `0` means "now".
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for QueryTime {
+    fn from(val: time::OffsetDateTime) -> Self {
+        Self::from_epoch_millis(val.unix_timestamp_nanos() / 1_000_000)
+    }
+}
+
+/// Create a relative query time the specified duration before "now". The sign of the
+/// duration is ignored.
+#[cfg(feature = "time")]
+impl From<time::Duration> for QueryTime {
+    fn from(val: time::Duration) -> Self {
+        Self::from_millis_ago(val.whole_milliseconds().unsigned_abs())
+    }
+}
+
+impl From<&str> for QueryTime {
     fn from(val: &str) -> Self {
         Self::from(String::from(val))
     }
@@ -111,10 +392,127 @@ impl From<String> for QueryTime {
     }
 }
 
+/// Parse a query time, accepting `0` for "now", positive epoch milliseconds, negative
+/// milliseconds ago, or a negative relative time made of one or more `<number><unit>` parts
+/// such as `-30m` or `-2h30m`.
+///
+/// The accepted units are `ms`, `s`, `m`, `h`, `d`, `w`, and `y`.
+impl FromStr for QueryTime {
+    type Err = ParseQueryTimeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(ParseQueryTimeError::Empty);
+        }
+
+        if let Ok(ms) = s.parse::<i64>() {
+            return Ok(Self::from(ms));
+        }
+
+        if let Ok(ts) = s.parse::<u64>() {
+            return Ok(Self::from(ts));
+        }
+
+        relative_units_ms(s)?;
+        Ok(Self(Inner::RelativeUnits(s.to_string())))
+    }
+}
+
+impl<'de> Deserialize<'de> for QueryTime {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        d.deserialize_any(QueryTimeVisitor)
+    }
+}
+
+struct QueryTimeVisitor;
+
+impl<'de> Visitor<'de> for QueryTimeVisitor {
+    type Value = QueryTime;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("milliseconds or a relative time such as \"-30m\"")
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(QueryTime::from(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(QueryTime::from(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        v.parse().map_err(E::custom)
+    }
+}
+
+/// An error encountered parsing a [`QueryTime`], or resolving a window of them.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum ParseQueryTimeError {
+    #[error("Query time is empty")]
+    Empty,
+    #[error(
+        "'{0}' is not a valid query time; expected milliseconds or a relative time such as '-30m'"
+    )]
+    Invalid(String),
+    #[error("'{unit}' in '{input}' is not a time unit; expected one of ms, s, m, h, d, w, y")]
+    UnknownUnit { input: String, unit: String },
+    #[error("'{0}' is too far from the present to represent")]
+    Overflow(String),
+    #[error("Window starts at {from}, after it ends at {until}")]
+    FromAfterUntil { from: u64, until: u64 },
+}
+
+/// Get the number of milliseconds before "now" described by a relative time such as `-2h30m`.
+fn relative_units_ms(input: &str) -> Result<u64, ParseQueryTimeError> {
+    let invalid = || ParseQueryTimeError::Invalid(input.to_string());
+    let mut rest = input.strip_prefix('-').ok_or_else(invalid)?;
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut total: u64 = 0;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(invalid)?;
+        if digits == 0 {
+            return Err(invalid());
+        }
+
+        let amount: u64 = rest[..digits]
+            .parse()
+            .map_err(|_| ParseQueryTimeError::Overflow(input.to_string()))?;
+        rest = &rest[digits..];
+
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let unit = &rest[..unit_len];
+        let (unit, scale) = UNITS
+            .iter()
+            .find(|(name, _)| *name == unit)
+            .ok_or_else(|| ParseQueryTimeError::UnknownUnit {
+                input: input.to_string(),
+                unit: unit.to_string(),
+            })?;
+        rest = &rest[unit.len()..];
+
+        total = amount
+            .checked_mul(*scale)
+            .and_then(|ms| total.checked_add(ms))
+            .ok_or_else(|| ParseQueryTimeError::Overflow(input.to_string()))?;
+    }
+
+    Ok(total)
+}
+
 #[cfg(test)]
 mod tests {
-    use super::QueryTime;
-    use serde_json;
+    use super::{ParseQueryTimeError, QueryRange, QueryTime};
+    use std::time::{Duration, UNIX_EPOCH};
 
     #[test]
     fn serialize_time_string() {
@@ -144,4 +542,161 @@ mod tests {
             serde_json::to_string(&QueryTime::from(-123i64)).unwrap()
         )
     }
+
+    #[test]
+    fn parse_valid() {
+        for input in &[
+            "0",
+            "1600000000000",
+            "-30000",
+            "-30m",
+            "-1w",
+            "-2h30m",
+            "-500ms",
+        ] {
+            assert!(input.parse::<QueryTime>().is_ok(), "{} should parse", input);
+        }
+
+        assert!("0".parse::<QueryTime>().unwrap().is_relative());
+        assert!("1600000000000".parse::<QueryTime>().unwrap().is_absolute());
+        assert_eq!(
+            r#""-2h30m""#,
+            serde_json::to_string(&"-2h30m".parse::<QueryTime>().unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert_eq!(
+            Err(ParseQueryTimeError::Empty),
+            "".parse::<QueryTime>().map(|_| ())
+        );
+        assert_eq!(
+            Err(ParseQueryTimeError::UnknownUnit {
+                input: "-30x".into(),
+                unit: "x".into()
+            }),
+            "-30x".parse::<QueryTime>().map(|_| ())
+        );
+
+        for input in &["30m", "-", "-m", "30 minutes", "-1.5h", "later"] {
+            assert!(
+                input.parse::<QueryTime>().is_err(),
+                "{} should not parse",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn display() {
+        for input in &["0", "123", "-123", "-2h30m"] {
+            assert_eq!(*input, input.parse::<QueryTime>().unwrap().to_string());
+        }
+    }
+
+    #[test]
+    fn deserialize() {
+        let times: Vec<QueryTime> = serde_json::from_str(r#"[0, 123, -123, "-30m"]"#).unwrap();
+        assert_eq!(
+            r#"[0,123,-123,"-30m"]"#,
+            serde_json::to_string(&times).unwrap()
+        );
+
+        assert!(serde_json::from_str::<QueryTime>(r#""soon""#).is_err());
+    }
+
+    #[test]
+    fn resolve() {
+        let now = 10_000_000;
+        assert_eq!(Ok(1_000_000), QueryTime::from("-2h30m").resolve(now));
+        assert_eq!(Ok(now - 123), QueryTime::from(-123i64).resolve(now));
+        assert_eq!(Ok(0), QueryTime::from("-1y").resolve(now));
+        assert!(matches!(
+            QueryTime::from("yesterday").resolve(now),
+            Err(ParseQueryTimeError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn range() {
+        let range =
+            QueryRange::resolve(&"-30m".into(), &(-10 * 60_000i64).into(), 3_600_000).unwrap();
+        assert_eq!(QueryRange::new(1_800_000, 3_000_000), range);
+        assert!(range.contains(1_800_000));
+        assert!(!range.contains(3_000_000));
+        assert!(range.contains_range(&QueryRange::new(2_000_000, 3_000_000)));
+        assert!(!range.contains_range(&QueryRange::new(2_000_000, 3_000_001)));
+        assert!(range.overlaps(&QueryRange::new(0, 1_800_001)));
+        assert!(!range.overlaps(&QueryRange::new(0, 1_800_000)));
+    }
+
+    #[test]
+    fn reversed_range() {
+        assert_eq!(
+            Err(ParseQueryTimeError::FromAfterUntil {
+                from: 3_000_000,
+                until: 1_800_000
+            }),
+            QueryRange::resolve(&(-10 * 60_000i64).into(), &"-30m".into(), 3_600_000)
+        );
+
+        let range = QueryRange::new(3_000_000, 1_800_000);
+        assert!(range.is_empty());
+        assert_eq!(3_000_000, range.from());
+    }
+
+    #[test]
+    fn split_range() {
+        assert_eq!(
+            vec![
+                QueryRange::new(0, 40),
+                QueryRange::new(40, 80),
+                QueryRange::new(80, 100)
+            ],
+            QueryRange::new(0, 100).split(Duration::from_millis(40))
+        );
+        assert!(QueryRange::new(5, 5)
+            .split(Duration::from_secs(1))
+            .is_empty());
+    }
+
+    #[test]
+    fn std_time() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_600_000_000_123);
+        assert_eq!(Some(time), QueryTime::from(time).to_system_time());
+        assert_eq!(
+            "1",
+            serde_json::to_string(&QueryTime::from(UNIX_EPOCH)).unwrap()
+        );
+        assert_eq!(
+            "0",
+            serde_json::to_string(&QueryTime::from(Duration::from_micros(999))).unwrap()
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono() {
+        use chrono::TimeZone;
+
+        let time = chrono::Utc.timestamp_millis_opt(1_600_000_000_123).unwrap();
+        assert_eq!(Ok(1_600_000_000_123), QueryTime::from(time).resolve(0));
+        assert_eq!(
+            "-3600000",
+            serde_json::to_string(&QueryTime::from(chrono::Duration::hours(-1))).unwrap()
+        );
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn time() {
+        let time =
+            time::OffsetDateTime::from_unix_timestamp_nanos(1_600_000_000_123_456_789).unwrap();
+        assert_eq!(Ok(1_600_000_000_123), QueryTime::from(time).resolve(0));
+        assert_eq!(
+            "-90000",
+            serde_json::to_string(&QueryTime::from(time::Duration::seconds(90))).unwrap()
+        );
+    }
 }
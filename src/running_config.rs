@@ -0,0 +1,250 @@
+//! Typed access to `/api/v1/runningconfig`.
+//!
+//! The running config is only available when talking directly to an appliance, so functions
+//! in this module take [`Appliance`] rather than [`Client`](crate::Client).
+//!
+//! # Example
+//! ```rust,ignore
+//! use extrahop::running_config::{self, RunningConfig};
+//!
+//! // Snapshot the config before an upgrade...
+//! running_config::get(&appliance).await?.save("before.json")?;
+//!
+//! // ...and afterwards, see what changed.
+//! let before = RunningConfig::load("before.json")?;
+//! let after = running_config::get(&appliance).await?;
+//! for change in before.diff(&after) {
+//!     println!("{}", change);
+//! }
+//! ```
+
+use crate::client::Appliance;
+use crate::{ApiResponse, Error};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+/// A snapshot of an appliance's running config.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RunningConfig(Value);
+
+impl RunningConfig {
+    /// Wrap a running config which was obtained some other way.
+    pub fn new(value: Value) -> Self {
+        RunningConfig(value)
+    }
+
+    /// Get the top-level section with the specified name, such as `capture`.
+    pub fn section(&self, name: &str) -> Option<&Value> {
+        self.0.get(name)
+    }
+
+    /// Get read access to the underlying JSON.
+    pub fn as_value(&self) -> &Value {
+        &self.0
+    }
+
+    /// Unwrap the underlying JSON.
+    pub fn into_value(self) -> Value {
+        self.0
+    }
+
+    /// Read a snapshot written by [`RunningConfig::save`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+
+    /// Write the snapshot to a JSON file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), &self.0)?;
+        Ok(())
+    }
+
+    /// Apply a [JSON merge patch](https://tools.ietf.org/html/rfc7386) to the config.
+    ///
+    /// Objects in the patch are merged recursively, `null` removes a property, and any other
+    /// value replaces the existing one. This allows changing part of a section without
+    /// restating the rest of it.
+    pub fn merge_patch(&mut self, patch: &Value) {
+        merge_patch(&mut self.0, patch);
+    }
+
+    /// List the differences between this config and `other`, ordered by path.
+    ///
+    /// Objects are compared property by property; arrays and other values are compared as a
+    /// whole.
+    pub fn diff(&self, other: &RunningConfig) -> Vec<ConfigChange> {
+        let mut changes = vec![];
+        diff_values(String::new(), &self.0, &other.0, &mut changes);
+        changes
+    }
+}
+
+/// A difference between two running config snapshots.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigChange {
+    /// The [JSON pointer](https://tools.ietf.org/html/rfc6901) to the changed value, such as
+    /// `/capture/ports`.
+    pub path: String,
+    /// The value in the first snapshot, or `None` if the property was added.
+    pub before: Option<Value>,
+    /// The value in the second snapshot, or `None` if the property was removed.
+    pub after: Option<Value>,
+}
+
+impl fmt::Display for ConfigChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.before, &self.after) {
+            (None, Some(after)) => write!(f, "+ {}: {}", self.path, after),
+            (Some(before), None) => write!(f, "- {}: {}", self.path, before),
+            (Some(before), Some(after)) => write!(f, "~ {}: {} -> {}", self.path, before, after),
+            (None, None) => write!(f, "  {}", self.path),
+        }
+    }
+}
+
+/// Get the appliance's running config.
+pub async fn get(appliance: &Appliance) -> Result<RunningConfig, Error> {
+    appliance
+        .get("v1/runningconfig")?
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Replace the appliance's running config.
+///
+/// The change takes effect immediately, but is lost on restart unless followed by
+/// [`save_as_default`].
+pub async fn put(appliance: &Appliance, config: &RunningConfig) -> Result<(), Error> {
+    appliance
+        .put("v1/runningconfig")?
+        .json(config)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+/// Apply a JSON merge patch to the appliance's current running config and write it back.
+///
+/// See [`RunningConfig::merge_patch`] for how the patch is applied.
+pub async fn patch(appliance: &Appliance, patch: &Value) -> Result<RunningConfig, Error> {
+    let mut config = get(appliance).await?;
+    config.merge_patch(patch);
+    put(appliance, &config).await?;
+    Ok(config)
+}
+
+/// Save the current running config so it persists across restarts.
+pub async fn save_as_default(appliance: &Appliance) -> Result<(), Error> {
+    appliance
+        .post("v1/runningconfig/save")?
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+fn merge_patch(target: &mut Value, patch: &Value) {
+    let patch = match patch {
+        Value::Object(patch) => patch,
+        other => {
+            *target = other.clone();
+            return;
+        }
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+
+    if let Value::Object(target) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(key);
+            } else {
+                merge_patch(target.entry(key.as_str()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+fn diff_values(path: String, before: &Value, after: &Value, changes: &mut Vec<ConfigChange>) {
+    match (before, after) {
+        (Value::Object(before), Value::Object(after)) => {
+            let mut keys: Vec<&String> = before.keys().chain(after.keys()).collect();
+            keys.sort();
+            keys.dedup();
+
+            for key in keys {
+                let path = format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"));
+                match (before.get(key), after.get(key)) {
+                    (Some(b), Some(a)) => diff_values(path, b, a, changes),
+                    (b, a) => changes.push(ConfigChange {
+                        path,
+                        before: b.cloned(),
+                        after: a.cloned(),
+                    }),
+                }
+            }
+        }
+        (before, after) if before != after => changes.push(ConfigChange {
+            path,
+            before: Some(before.clone()),
+            after: Some(after.clone()),
+        }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RunningConfig;
+    use serde_json::json;
+
+    #[test]
+    fn merge_patch() {
+        let mut config = RunningConfig::new(json!({
+            "capture": { "enabled": true, "ports": [80] },
+            "ntp": { "servers": ["a"] }
+        }));
+
+        config.merge_patch(&json!({ "capture": { "ports": [80, 443] }, "ntp": null }));
+
+        assert_eq!(
+            &json!({ "capture": { "enabled": true, "ports": [80, 443] } }),
+            config.as_value()
+        );
+    }
+
+    #[test]
+    fn diff() {
+        let before = RunningConfig::new(json!({
+            "capture": { "enabled": true, "ports": [80] },
+            "a/b": 1
+        }));
+        let after = RunningConfig::new(json!({
+            "capture": { "enabled": true, "ports": [80, 443], "snaplen": 96 }
+        }));
+
+        let changes: Vec<String> = before.diff(&after).iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            vec![
+                "- /a~1b: 1",
+                "~ /capture/ports: [80] -> [80,443]",
+                "+ /capture/snaplen: 96"
+            ],
+            changes
+        );
+    }
+}
@@ -0,0 +1,196 @@
+//! Shared types for reconciling an appliance with definitions kept outside it, such as in
+//! source control.
+//!
+//! Objects are matched by name: definitions with no matching object are created, objects
+//! which differ from their definition are replaced by it, and - if requested - objects with
+//! no definition are deleted.
+
+use crate::Error;
+use std::collections::HashMap;
+
+/// Controls how definitions are reconciled with the objects on an appliance.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncOptions {
+    /// Delete objects on the appliance which have no matching definition.
+    pub delete_missing: bool,
+    /// Compute the changes which would be made without making them.
+    pub dry_run: bool,
+}
+
+/// The outcome of reconciling an appliance, listing object names by what happened to them.
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub deleted: Vec<String>,
+    pub unchanged: Vec<String>,
+    /// Objects whose change was attempted but rejected by the appliance.
+    pub failed: Vec<(String, Error)>,
+}
+
+impl SyncReport {
+    /// Returns `true` if every change was applied successfully.
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// Record the outcome of applying a planned change.
+    pub(crate) fn record<D>(
+        &mut self,
+        name: String,
+        change: &Change<'_, D>,
+        result: Result<(), Error>,
+    ) {
+        let list = match (change, result) {
+            (_, Err(e)) => {
+                self.failed.push((name, e));
+                return;
+            }
+            (Change::Create(_), Ok(())) => &mut self.created,
+            (Change::Update(..), Ok(())) => &mut self.updated,
+            (Change::Delete(_), Ok(())) => &mut self.deleted,
+        };
+
+        list.push(name);
+    }
+}
+
+/// A definition of an object which can be reconciled with an object of type `R` on an
+/// appliance.
+pub(crate) trait Definition<R> {
+    fn name(&self) -> &str;
+
+    /// Returns `true` if the object on the appliance already matches the definition.
+    fn matches(&self, remote: &R) -> bool;
+}
+
+/// An object on an appliance which can be reconciled with a definition.
+pub(crate) trait Remote {
+    fn name(&self) -> &str;
+    fn id(&self) -> u64;
+}
+
+/// A change needed to make the appliance match a definition.
+pub(crate) enum Change<'a, D> {
+    Create(&'a D),
+    /// Replace the object with the specified ID using the definition.
+    Update(u64, &'a D),
+    /// Delete the object with the specified ID.
+    Delete(u64),
+}
+
+/// The changes needed to make an appliance match a set of definitions.
+pub(crate) struct Plan<'a, D> {
+    pub unchanged: Vec<String>,
+    pub changes: Vec<(String, Change<'a, D>)>,
+}
+
+impl<'a, D> Plan<'a, D> {
+    /// Compute the changes needed to make `remote` match `definitions`.
+    ///
+    /// Changes to definitions are listed in the order of `definitions`, followed by any
+    /// deletions sorted by name.
+    pub fn new<R: Remote>(definitions: &'a [D], remote: Vec<R>, options: &SyncOptions) -> Self
+    where
+        D: Definition<R>,
+    {
+        let mut remote: HashMap<String, R> = remote
+            .into_iter()
+            .map(|object| (object.name().to_string(), object))
+            .collect();
+
+        let mut plan = Plan {
+            unchanged: vec![],
+            changes: vec![],
+        };
+
+        for definition in definitions {
+            let name = definition.name().to_string();
+            match remote.remove(&name) {
+                Some(object) if definition.matches(&object) => plan.unchanged.push(name),
+                Some(object) => plan
+                    .changes
+                    .push((name, Change::Update(object.id(), definition))),
+                None => plan.changes.push((name, Change::Create(definition))),
+            }
+        }
+
+        if options.delete_missing {
+            let mut extra: Vec<_> = remote.into_iter().collect();
+            extra.sort_by(|a, b| a.0.cmp(&b.0));
+            for (name, object) in extra {
+                plan.changes.push((name, Change::Delete(object.id())));
+            }
+        }
+
+        plan
+    }
+
+    /// Start a report which already includes the unchanged objects.
+    pub fn report(&mut self) -> SyncReport {
+        SyncReport {
+            unchanged: std::mem::take(&mut self.unchanged),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Change, Definition, Plan, Remote, SyncOptions};
+
+    struct Local(&'static str, u8);
+    struct Object(&'static str, u64, u8);
+
+    impl Definition<Object> for Local {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn matches(&self, remote: &Object) -> bool {
+            self.1 == remote.2
+        }
+    }
+
+    impl Remote for Object {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn id(&self) -> u64 {
+            self.1
+        }
+    }
+
+    #[test]
+    fn plan_changes() {
+        let local = vec![Local("a", 1), Local("b", 2), Local("c", 3)];
+        let remote = vec![Object("a", 10, 1), Object("b", 11, 0), Object("z", 12, 0)];
+        let options = SyncOptions {
+            delete_missing: true,
+            ..Default::default()
+        };
+
+        let plan = Plan::new(&local, remote, &options);
+        assert_eq!(vec!["a".to_string()], plan.unchanged);
+
+        let summary: Vec<_> = plan
+            .changes
+            .iter()
+            .map(|(name, change)| match change {
+                Change::Create(_) => format!("create {}", name),
+                Change::Update(id, _) => format!("update {} #{}", name, id),
+                Change::Delete(id) => format!("delete {} #{}", name, id),
+            })
+            .collect();
+
+        assert_eq!(vec!["update b #11", "create c", "delete z #12"], summary);
+    }
+
+    #[test]
+    fn plan_keeps_missing_by_default() {
+        let local: Vec<Local> = vec![];
+        let plan = Plan::new(&local, vec![Object("z", 12, 0)], &SyncOptions::default());
+        assert!(plan.changes.is_empty());
+    }
+}
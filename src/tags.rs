@@ -0,0 +1,216 @@
+//! Typed operations for `/api/v1/tags`.
+//!
+//! Tags are frequently populated from an external inventory such as a CMDB. [`sync_devices`]
+//! makes a tag's devices match such a list in one call, creating the tag if needed.
+
+use crate::api_response::created_id;
+use crate::oid::Device;
+use crate::{ApiResponse, Assignment, Client, Error, Oid};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// The largest number of devices that will be sent in a single tagging request.
+pub const ASSIGNMENT_BATCH_SIZE: usize = 1000;
+
+/// A device tag as returned by the REST API.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Tag {
+    pub id: u64,
+    pub name: String,
+    /// The time the tag was last modified, in milliseconds since epoch.
+    #[serde(default)]
+    pub mod_time: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct TagName<'a> {
+    name: &'a str,
+}
+
+/// A device as returned by the tag membership endpoint; only the ID is retained.
+#[derive(Deserialize)]
+struct Tagged {
+    id: Oid<Device>,
+}
+
+/// The outcome of a batched change to the devices with a tag.
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    /// The number of devices in batches which were accepted by the appliance.
+    pub succeeded: usize,
+    /// The batches which were rejected, along with the reason.
+    pub failed: Vec<(Assignment, Error)>,
+}
+
+impl BatchReport {
+    /// Returns `true` if every batch was accepted.
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Get all tags on the appliance.
+pub async fn list(client: &Client) -> Result<Vec<Tag>, Error> {
+    client
+        .get("v1/tags")?
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Get a single tag by ID.
+pub async fn get(client: &Client, id: u64) -> Result<Tag, Error> {
+    client
+        .get(&format!("v1/tags/{}", id))?
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Create a new tag, returning the ID assigned by the appliance.
+pub async fn create(client: &Client, name: &str) -> Result<u64, Error> {
+    let response = client
+        .post("v1/tags")?
+        .json(&TagName { name })
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    created_id(&response)
+}
+
+/// Rename an existing tag.
+pub async fn rename(client: &Client, id: u64, name: &str) -> Result<(), Error> {
+    client
+        .patch(&format!("v1/tags/{}", id))?
+        .json(&TagName { name })
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+/// Delete a tag, removing it from every device.
+pub async fn delete(client: &Client, id: u64) -> Result<(), Error> {
+    client
+        .delete(&format!("v1/tags/{}", id))?
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+/// Get the IDs of the devices which currently have a tag.
+pub async fn devices(client: &Client, id: u64) -> Result<Vec<Oid<Device>>, Error> {
+    Ok(client
+        .get(&format!("v1/tags/{}/devices", id))?
+        .send()
+        .await?
+        .validate_and_read::<Vec<Tagged>>()
+        .await?
+        .into_iter()
+        .map(|device| device.id)
+        .collect())
+}
+
+/// Add a tag to, and remove it from, devices.
+///
+/// Large changes are sent in batches of at most [`ASSIGNMENT_BATCH_SIZE`] devices. Unlike
+/// most functions in this crate, a rejected batch does not stop later batches from being
+/// sent; inspect the returned report to find out which batches failed.
+pub async fn update_devices(client: &Client, id: u64, change: &Assignment) -> BatchReport {
+    let mut report = BatchReport::default();
+    let endpoint = format!("v1/tags/{}/devices", id);
+
+    for batch in change.batches(ASSIGNMENT_BATCH_SIZE) {
+        match send_batch(client, &endpoint, &batch).await {
+            Ok(()) => report.succeeded += batch.assign.len() + batch.unassign.len(),
+            Err(e) => report.failed.push((batch, e)),
+        }
+    }
+
+    report
+}
+
+/// Make the devices with the tag named `name` exactly match `devices`, creating the tag if
+/// it does not exist yet.
+///
+/// Only the devices whose tagging needs to change are sent to the appliance. An error is
+/// returned if the tag or its current devices could not be retrieved; otherwise, failures
+/// are reported per batch.
+pub async fn sync_devices(
+    client: &Client,
+    name: &str,
+    devices: &[Oid<Device>],
+) -> Result<BatchReport, Error> {
+    let existing = list(client).await?.into_iter().find(|tag| tag.name == name);
+    let (id, current) = match existing {
+        Some(tag) => (tag.id, self::devices(client, tag.id).await?),
+        None => (create(client, name).await?, vec![]),
+    };
+
+    Ok(update_devices(client, id, &diff(&current, devices)).await)
+}
+
+/// Compute the assignment which turns `current` into `desired`, preserving the order of
+/// the inputs.
+fn diff(current: &[Oid<Device>], desired: &[Oid<Device>]) -> Assignment {
+    let current_set: HashSet<_> = current.iter().collect();
+    let desired_set: HashSet<_> = desired.iter().collect();
+
+    let mut seen = HashSet::new();
+    Assignment {
+        assign: desired
+            .iter()
+            .filter(|id| !current_set.contains(id) && seen.insert(*id))
+            .map(|id| id.untyped())
+            .collect(),
+        unassign: current
+            .iter()
+            .filter(|id| !desired_set.contains(id))
+            .map(|id| id.untyped())
+            .collect(),
+    }
+}
+
+async fn send_batch(client: &Client, endpoint: &str, batch: &Assignment) -> Result<(), Error> {
+    client
+        .post(endpoint)?
+        .json(batch)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::diff;
+    use crate::Oid;
+
+    fn oids<K>(ids: &[u64]) -> Vec<Oid<K>> {
+        ids.iter().cloned().map(Oid::new).collect()
+    }
+
+    #[test]
+    fn diff_devices() {
+        let change = diff(&oids(&[1, 2, 3]), &oids(&[3, 4, 4, 5, 1]));
+        assert_eq!(oids(&[4, 5]), change.assign);
+        assert_eq!(oids(&[2]), change.unassign);
+    }
+
+    #[test]
+    fn diff_unchanged() {
+        assert!(diff(&oids(&[1, 2]), &oids(&[2, 1])).is_empty());
+    }
+}
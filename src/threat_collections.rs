@@ -0,0 +1,197 @@
+//! Typed operations for `/api/v1/threatcollections`.
+//!
+//! Threat collections are STIX files of observables, such as IP addresses and hostnames,
+//! which Reveal(x) matches against observed traffic. Collections are uploaded as multipart
+//! form data, either from a file on disk or from a buffer already in memory.
+
+use crate::{path, ApiResponse, Client, Error, RestError};
+use reqwest::multipart::{Form, Part};
+use reqwest::{RequestBuilder, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+use thiserror::Error;
+
+/// A threat collection as returned by the REST API.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ThreatCollection {
+    pub id: u64,
+    pub name: String,
+    /// The key used to replace the contents of a user-uploaded collection.
+    #[serde(default)]
+    pub user_key: Option<String>,
+    /// Whether the collection was uploaded by a user rather than provided by ExtraHop.
+    #[serde(default)]
+    pub is_user_uploaded: bool,
+    /// The time the collection was last updated, in milliseconds since epoch.
+    #[serde(default)]
+    pub last_updated: Option<u64>,
+    /// The number of observables of each type in the collection.
+    #[serde(default)]
+    pub observables: Option<serde_json::Value>,
+}
+
+/// The contents of a STIX file to upload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StixFile {
+    /// The file name reported to the appliance, such as `feed.tgz`.
+    pub file_name: String,
+    pub contents: Vec<u8>,
+}
+
+impl StixFile {
+    /// Create a STIX file from a buffer in memory.
+    pub fn new(file_name: impl Into<String>, contents: impl Into<Vec<u8>>) -> Self {
+        Self {
+            file_name: file_name.into(),
+            contents: contents.into(),
+        }
+    }
+
+    /// Read a STIX file from disk.
+    pub async fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        Ok(Self::new(file_name, tokio::fs::read(path).await?))
+    }
+
+    fn into_part(self) -> Part {
+        Part::bytes(self.contents).file_name(self.file_name)
+    }
+}
+
+/// An error encountered while uploading a threat collection.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum UploadError {
+    #[error("Unable to read STIX file")]
+    Io(#[from] io::Error),
+    /// The appliance received the file but failed to validate it, e.g. because it is not a
+    /// valid STIX file. Other errors, such as authentication failures, are reported as
+    /// [`UploadError::Client`].
+    #[error("Threat collection '{name}' was rejected: {reason}")]
+    Rejected { name: String, reason: RestError },
+    #[error("Unable to upload threat collection")]
+    Client(#[from] Error),
+}
+
+/// Get all threat collections on the appliance.
+pub async fn list(client: &Client) -> Result<Vec<ThreatCollection>, Error> {
+    client
+        .get("v1/threatcollections")?
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Upload a STIX file as a new threat collection.
+pub async fn upload(client: &Client, name: &str, file: StixFile) -> Result<(), UploadError> {
+    let form = Form::new()
+        .text("name", name.to_string())
+        .part("file", file.into_part());
+
+    send_upload(client.post("v1/threatcollections")?, name, form).await
+}
+
+/// Read a STIX file from disk and upload it as a new threat collection.
+pub async fn upload_file(
+    client: &Client,
+    name: &str,
+    path: impl AsRef<Path>,
+) -> Result<(), UploadError> {
+    upload(client, name, StixFile::read(path).await?).await
+}
+
+/// Replace the contents of the user-uploaded collection identified by `user_key`.
+pub async fn replace(client: &Client, user_key: &str, file: StixFile) -> Result<(), UploadError> {
+    let form = Form::new().part("file", file.into_part());
+    send_upload(
+        client.put(&format!(
+            "v1/threatcollections/~{}",
+            path::segment(user_key)
+        ))?,
+        user_key,
+        form,
+    )
+    .await
+}
+
+/// Delete a threat collection.
+pub async fn delete(client: &Client, id: u64) -> Result<(), Error> {
+    client
+        .delete(&format!("v1/threatcollections/{}", id))?
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+async fn send_upload(request: RequestBuilder, name: &str, form: Form) -> Result<(), UploadError> {
+    match request
+        .multipart(form)
+        .send()
+        .await
+        .map_err(Error::from)?
+        .validate_status()
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(Error::Rest(reason)) if is_rejection(reason.status()) => Err(UploadError::Rejected {
+            name: name.to_string(),
+            reason,
+        }),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Returns `true` if the status means the appliance found a problem with the upload itself.
+fn is_rejection(status: StatusCode) -> bool {
+    status == StatusCode::BAD_REQUEST || status == StatusCode::UNPROCESSABLE_ENTITY
+}
+
+impl From<url::ParseError> for UploadError {
+    fn from(e: url::ParseError) -> Self {
+        UploadError::Client(e.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_rejection, UploadError};
+    use crate::RestError;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn rejected_message() {
+        let error = UploadError::Rejected {
+            name: "Internal Feed".into(),
+            reason: RestError::new(
+                StatusCode::BAD_REQUEST,
+                Some("Invalid STIX file".to_string()),
+            ),
+        };
+
+        assert_eq!(
+            "Threat collection 'Internal Feed' was rejected: 400/Bad Request: Invalid STIX file",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn only_validation_errors_are_rejections() {
+        assert!(is_rejection(StatusCode::BAD_REQUEST));
+        assert!(is_rejection(StatusCode::UNPROCESSABLE_ENTITY));
+        assert!(!is_rejection(StatusCode::UNAUTHORIZED));
+        assert!(!is_rejection(StatusCode::FORBIDDEN));
+        assert!(!is_rejection(StatusCode::NOT_FOUND));
+        assert!(!is_rejection(StatusCode::INTERNAL_SERVER_ERROR));
+    }
+}
@@ -0,0 +1,266 @@
+//! Split long queries into shorter ones which each cover part of the original window.
+//!
+//! Queries spanning days or weeks can time out on busy appliances. [`run`] splits a query's
+//! window into fixed-size slices and sends one query per slice, with a bounded number in
+//! flight at once.
+//!
+//! Slices are always absolute. A relative window is resolved once before any query is sent,
+//! so the slices stay contiguous however long the run takes; if each slice were sent as a
+//! relative time, the appliance would resolve it against its own "now" when that request
+//! ran, leaving gaps or overlaps between slices.
+//!
+//! # Example
+//! ```rust,ignore
+//! use extrahop::activitymap::{Query, Response};
+//! use extrahop::{time_slices, ApiResponse};
+//! use std::time::Duration;
+//!
+//! let query = Query::builder().from("-1w").walks(walks).build()?;
+//! let hourly = time_slices::run(&query, Duration::from_secs(3600), 4, |query| async move {
+//!     client
+//!         .post("v1/activitymaps/query")?
+//!         .json(&query)
+//!         .send()
+//!         .await?
+//!         .validate_and_read::<Response>()
+//!         .await
+//! })
+//! .await?;
+//!
+//! for sliced in hourly {
+//!     println!("{:?}: {} edges", sliced.slice.from, sliced.result?.edges.len());
+//! }
+//! ```
+
+use crate::query_time::now_millis;
+use crate::{ParseQueryTimeError, QueryRange, QueryTime};
+use futures::stream::{self, StreamExt};
+use std::future::Future;
+use std::time::Duration;
+use thiserror::Error;
+
+/// A query which covers a window of time.
+pub trait Windowed: Clone {
+    /// Get the start and end of the query's window.
+    fn window(&self) -> (QueryTime, QueryTime);
+
+    /// Create a copy of the query which covers a different window.
+    fn with_window(&self, from: QueryTime, until: QueryTime) -> Self;
+}
+
+/// Part of a query's window.
+#[derive(Debug, Clone)]
+pub struct TimeSlice {
+    /// The position of the slice in the window, starting from 0 for the earliest slice.
+    pub index: usize,
+    pub from: QueryTime,
+    pub until: QueryTime,
+}
+
+/// The outcome of the query for a single slice.
+#[derive(Debug)]
+pub struct Sliced<T, E> {
+    pub slice: TimeSlice,
+    pub result: Result<T, E>,
+}
+
+/// An error encountered splitting a window into slices.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum SliceError {
+    #[error("Unable to slice window")]
+    Time(#[from] ParseQueryTimeError),
+}
+
+/// Split the window from `from` to `until` into consecutive absolute slices of `size`, in
+/// order, resolving relative times against `now` in milliseconds since epoch.
+///
+/// The last slice is shorter if the window is not a whole number of slices long. Returns an
+/// error if the window ends before it starts.
+///
+/// # Panics
+/// This function will panic if `size` is less than one millisecond.
+pub fn split(
+    from: &QueryTime,
+    until: &QueryTime,
+    size: Duration,
+    now: u64,
+) -> Result<Vec<TimeSlice>, SliceError> {
+    // `0` means "now" to the appliance, so a slice starting at the epoch starts 1ms after it.
+    let to_query_time = |time: u64| QueryTime::from(time.max(1));
+
+    Ok(QueryRange::resolve(from, until, now)?
+        .split(size)
+        .into_iter()
+        .enumerate()
+        .map(|(index, range)| TimeSlice {
+            index,
+            from: to_query_time(range.from()),
+            until: to_query_time(range.until()),
+        })
+        .collect())
+}
+
+/// Split the query's window into slices of `size` and call `f` with a copy of the query for
+/// each slice, with at most `concurrency` calls running at once.
+///
+/// Relative times in the window are resolved once against the local clock before any query is
+/// sent. If the local clock may differ from the appliance's, resolve the window against the
+/// appliance's time with [`QueryRange::resolve`] first, such as the `until` of a recent
+/// response.
+///
+/// Results are returned in slice order, regardless of the order in which the queries finish.
+/// An error is returned only if the window could not be split; see [`split`].
+///
+/// # Panics
+/// This function will panic if `size` is less than one millisecond or `concurrency` is 0.
+pub async fn run<Q, F, Fut, T, E>(
+    query: &Q,
+    size: Duration,
+    concurrency: usize,
+    f: F,
+) -> Result<Vec<Sliced<T, E>>, SliceError>
+where
+    Q: Windowed,
+    F: Fn(Q) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    assert!(concurrency > 0, "Slice concurrency must be at least 1");
+
+    let (from, until) = query.window();
+    let slices = split(&from, &until, size, now_millis())?;
+
+    Ok(stream::iter(slices)
+        .map(|slice| {
+            let request = f(query.with_window(slice.from.clone(), slice.until.clone()));
+            async move {
+                Sliced {
+                    result: request.await,
+                    slice,
+                }
+            }
+        })
+        .buffered(concurrency)
+        .collect()
+        .await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{split, Windowed};
+    use crate::QueryTime;
+    use std::time::Duration;
+
+    fn to_json(time: &QueryTime) -> String {
+        serde_json::to_string(time).unwrap()
+    }
+
+    #[test]
+    fn split_relative() {
+        let now = 10_000_000;
+        let slices = split(
+            &"-2h30m".into(),
+            &QueryTime::default(),
+            Duration::from_secs(3600),
+            now,
+        )
+        .unwrap();
+
+        let windows: Vec<_> = slices
+            .iter()
+            .map(|slice| (to_json(&slice.from), to_json(&slice.until)))
+            .collect();
+        assert_eq!(
+            vec![
+                ("1000000".to_string(), "4600000".to_string()),
+                ("4600000".to_string(), "8200000".to_string()),
+                ("8200000".to_string(), "10000000".to_string()),
+            ],
+            windows
+        );
+    }
+
+    #[test]
+    fn split_absolute() {
+        let slices = split(
+            &1_000u64.into(),
+            &3_500u64.into(),
+            Duration::from_secs(1),
+            10_000,
+        )
+        .unwrap();
+        assert_eq!(3, slices.len());
+        assert_eq!(2, slices[2].index);
+        assert_eq!("3000", to_json(&slices[2].from));
+        assert_eq!("3500", to_json(&slices[2].until));
+    }
+
+    #[test]
+    fn split_mixed() {
+        let slices = split(
+            &1_000u64.into(),
+            &QueryTime::default(),
+            Duration::from_secs(1),
+            2_500,
+        )
+        .unwrap();
+        assert_eq!(2, slices.len());
+        assert_eq!("2500", to_json(&slices[1].until));
+    }
+
+    #[test]
+    fn split_reversed() {
+        assert!(split(
+            &"-1h".into(),
+            &"-1d".into(),
+            Duration::from_secs(60),
+            100_000_000,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn split_from_epoch() {
+        let slices = split(
+            &"-1h".into(),
+            &QueryTime::default(),
+            Duration::from_secs(1),
+            500,
+        )
+        .unwrap();
+        assert_eq!(1, slices.len());
+        assert_eq!("1", to_json(&slices[0].from));
+    }
+
+    #[derive(Clone)]
+    struct Window(QueryTime, QueryTime);
+
+    impl Windowed for Window {
+        fn window(&self) -> (QueryTime, QueryTime) {
+            (self.0.clone(), self.1.clone())
+        }
+
+        fn with_window(&self, from: QueryTime, until: QueryTime) -> Self {
+            Window(from, until)
+        }
+    }
+
+    #[tokio::test]
+    async fn run_in_order() {
+        let results = super::run(
+            &Window("-3m".into(), QueryTime::default()),
+            Duration::from_secs(60),
+            2,
+            |window| async move { Ok::<_, ()>(window.window()) },
+        )
+        .await
+        .unwrap();
+
+        let windows: Vec<_> = results.into_iter().map(|r| r.result.unwrap()).collect();
+        assert_eq!(3, windows.len());
+        for pair in windows.windows(2) {
+            assert!(pair[0].0.is_absolute());
+            assert_eq!(to_json(&pair[0].1), to_json(&pair[1].0));
+        }
+    }
+}
@@ -0,0 +1,395 @@
+//! Typed operations for `/api/v1/triggers`.
+//!
+//! # Keeping Triggers in Source Control
+//! [`sync_directory`] reconciles an appliance against a directory of trigger definitions.
+//! Each trigger is stored as a pair of files sharing a stem, which is used as the trigger name:
+//!
+//! ```text
+//! triggers/
+//!     HTTP Latency.js      <- the trigger script
+//!     HTTP Latency.json    <- the remaining properties, e.g. {"events": ["HTTP_RESPONSE"]}
+//! ```
+
+use crate::api_response::created_id;
+use crate::sync::{Change, Definition, Plan, Remote, SyncOptions, SyncReport};
+use crate::{ApiResponse, Assignment, Client, Error};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use tokio::fs;
+
+/// A trigger as returned by the REST API.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Trigger {
+    pub id: u64,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub author: Option<String>,
+    /// The JavaScript source of the trigger.
+    #[serde(default)]
+    pub script: String,
+    /// The events which cause the trigger to run, such as `HTTP_RESPONSE`.
+    #[serde(default)]
+    pub events: Vec<String>,
+    #[serde(default)]
+    pub disabled: bool,
+    #[serde(default)]
+    pub debug: bool,
+    /// Whether the trigger runs on all applicable sources rather than only those it has
+    /// been assigned to.
+    #[serde(default)]
+    pub apply_all: bool,
+    /// Advanced options which control how the trigger is run.
+    #[serde(default)]
+    pub hints: Option<Value>,
+    /// The time the trigger was last modified, in milliseconds since epoch.
+    #[serde(default)]
+    pub mod_time: Option<u64>,
+}
+
+/// The user-controlled properties of a trigger, used to create or replace a trigger.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TriggerDefinition {
+    pub name: String,
+    pub description: String,
+    pub script: String,
+    pub events: Vec<String>,
+    pub disabled: bool,
+    pub debug: bool,
+    pub apply_all: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hints: Option<Value>,
+}
+
+impl TriggerDefinition {
+    /// Returns `true` if the trigger on the appliance already matches this definition.
+    ///
+    /// Hints are only compared if the definition specifies them, since the appliance fills
+    /// in defaults for any hints that are not sent.
+    pub fn matches(&self, trigger: &Trigger) -> bool {
+        self.name == trigger.name
+            && self.description == trigger.description
+            && self.script == trigger.script
+            && self.events == trigger.events
+            && self.disabled == trigger.disabled
+            && self.debug == trigger.debug
+            && self.apply_all == trigger.apply_all
+            && (self.hints.is_none() || self.hints == trigger.hints)
+    }
+}
+
+impl Definition<Trigger> for TriggerDefinition {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn matches(&self, remote: &Trigger) -> bool {
+        TriggerDefinition::matches(self, remote)
+    }
+}
+
+impl Remote for Trigger {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+/// Changes to apply to an existing trigger. Fields left as `None` are not changed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TriggerUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub events: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debug: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub apply_all: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hints: Option<Value>,
+}
+
+/// Get all triggers on the appliance.
+pub async fn list(client: &Client) -> Result<Vec<Trigger>, Error> {
+    client
+        .get("v1/triggers")?
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Get a single trigger by ID.
+pub async fn get(client: &Client, id: u64) -> Result<Trigger, Error> {
+    client
+        .get(&format!("v1/triggers/{}", id))?
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Create a new trigger, returning the ID assigned by the appliance.
+pub async fn create(client: &Client, definition: &TriggerDefinition) -> Result<u64, Error> {
+    let response = client
+        .post("v1/triggers")?
+        .json(definition)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    created_id(&response)
+}
+
+/// Apply changes to an existing trigger.
+pub async fn update(client: &Client, id: u64, update: &TriggerUpdate) -> Result<(), Error> {
+    client
+        .patch(&format!("v1/triggers/{}", id))?
+        .json(update)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+/// Delete a trigger.
+pub async fn delete(client: &Client, id: u64) -> Result<(), Error> {
+    client
+        .delete(&format!("v1/triggers/{}", id))?
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+/// Replace the script of an existing trigger.
+pub async fn upload_script(
+    client: &Client,
+    id: u64,
+    script: impl Into<String>,
+) -> Result<(), Error> {
+    update(
+        client,
+        id,
+        &TriggerUpdate {
+            script: Some(script.into()),
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+/// Enable or disable an existing trigger.
+pub async fn set_enabled(client: &Client, id: u64, enabled: bool) -> Result<(), Error> {
+    update(
+        client,
+        id,
+        &TriggerUpdate {
+            disabled: Some(!enabled),
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+/// Assign a trigger to, or unassign it from, specific devices.
+pub async fn assign_devices(client: &Client, id: u64, change: &Assignment) -> Result<(), Error> {
+    client
+        .post(&format!("v1/triggers/{}/devices", id))?
+        .json(change)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+/// Assign a trigger to, or unassign it from, device groups.
+pub async fn assign_device_groups(
+    client: &Client,
+    id: u64,
+    change: &Assignment,
+) -> Result<(), Error> {
+    client
+        .post(&format!("v1/triggers/{}/devicegroups", id))?
+        .json(change)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+/// Read every trigger definition from a directory laid out as described in the
+/// [module documentation](self).
+///
+/// Scripts without a matching `.json` file are loaded with default properties; `.json` files
+/// without a matching script are ignored. Definitions are returned sorted by name.
+pub async fn load_directory(path: impl AsRef<Path>) -> Result<Vec<TriggerDefinition>, Error> {
+    let mut definitions = BTreeMap::new();
+    let mut entries = fs::read_dir(path).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("js") {
+            continue;
+        }
+
+        let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+
+        let mut definition: TriggerDefinition =
+            match fs::read_to_string(path.with_extension("json")).await {
+                Ok(properties) => serde_json::from_str(&properties)?,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => TriggerDefinition::default(),
+                Err(e) => return Err(e.into()),
+            };
+
+        definition.script = fs::read_to_string(&path).await?;
+        definition.name = name.clone();
+        definitions.insert(name, definition);
+    }
+
+    Ok(definitions.into_values().collect())
+}
+
+/// Make the triggers on the appliance match the definitions in a directory.
+///
+/// Triggers are matched by name, as described in the [`sync`](crate::sync) module. A failure
+/// to change one trigger does not prevent the others from being changed; see
+/// [`SyncReport::failed`].
+pub async fn sync_directory(
+    client: &Client,
+    path: impl AsRef<Path>,
+    options: &SyncOptions,
+) -> Result<SyncReport, Error> {
+    let definitions = load_directory(path).await?;
+    let mut plan = Plan::new(&definitions, list(client).await?, options);
+    let mut report = plan.report();
+
+    for (name, change) in plan.changes {
+        let result = match (&change, options.dry_run) {
+            (_, true) => Ok(()),
+            (Change::Create(definition), false) => create(client, definition).await.map(|_| ()),
+            (Change::Update(id, definition), false) => replace(client, *id, definition).await,
+            (Change::Delete(id), false) => delete(client, *id).await,
+        };
+
+        report.record(name, &change, result);
+    }
+
+    Ok(report)
+}
+
+/// Overwrite every user-controlled property of a trigger with those in `definition`.
+async fn replace(client: &Client, id: u64, definition: &TriggerDefinition) -> Result<(), Error> {
+    client
+        .patch(&format!("v1/triggers/{}", id))?
+        .json(definition)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load_directory, Trigger, TriggerDefinition};
+    use serde_json::json;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// A scratch directory which is removed when dropped, even if the test fails.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(test: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "extrahop-triggers-{}-{}",
+                test,
+                std::process::id()
+            ));
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn load_definitions() {
+        let dir = TempDir::new("load_definitions");
+        fs::write(dir.0.join("Latency.js"), "debug('hi');").unwrap();
+        fs::write(
+            dir.0.join("Latency.json"),
+            r#"{"events": ["HTTP_RESPONSE"]}"#,
+        )
+        .unwrap();
+        fs::write(dir.0.join("Bare.js"), "").unwrap();
+        fs::write(dir.0.join("Orphan.json"), "{}").unwrap();
+
+        let definitions = load_directory(&dir.0).await.unwrap();
+
+        assert_eq!(2, definitions.len());
+        assert_eq!("Bare", definitions[0].name);
+        assert_eq!("Latency", definitions[1].name);
+        assert_eq!("debug('hi');", definitions[1].script);
+        assert_eq!(vec!["HTTP_RESPONSE".to_string()], definitions[1].events);
+    }
+
+    #[test]
+    fn matches_ignores_unspecified_hints() {
+        let trigger: Trigger = serde_json::from_value(json!({
+            "id": 19,
+            "name": "Latency",
+            "script": "debug('hi');",
+            "events": ["HTTP_RESPONSE"],
+            "hints": { "payload_bytes": 2048 }
+        }))
+        .unwrap();
+
+        let definition = TriggerDefinition {
+            name: "Latency".into(),
+            script: "debug('hi');".into(),
+            events: vec!["HTTP_RESPONSE".into()],
+            ..Default::default()
+        };
+
+        assert!(definition.matches(&trigger));
+        assert!(!TriggerDefinition {
+            disabled: true,
+            ..definition
+        }
+        .matches(&trigger));
+    }
+}
@@ -0,0 +1,13 @@
+//! Catch-all enum variants for values not known to this version of the crate.
+//!
+//! Such variants are marked `#[serde(other, serialize_with = "crate::unknown::reject")]`, so
+//! they can be read from responses but are never sent to the appliance.
+
+use serde::ser::{Error, Serializer};
+
+/// Fail to serialize a catch-all variant, since the value it was read from is not kept.
+pub(crate) fn reject<S: Serializer>(_serializer: S) -> Result<S::Ok, S::Error> {
+    Err(S::Error::custom(
+        "Unknown values received from the appliance cannot be sent back to it",
+    ))
+}
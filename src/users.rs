@@ -0,0 +1,393 @@
+//! Typed operations for `/api/v1/users`.
+//!
+//! # Offboarding
+//! Dashboards owned by a deleted user are lost unless they are handed to another user
+//! first. [`offboard`] does both, and leaves the user in place if any dashboard
+//! could not be transferred.
+//!
+//! ```rust,ignore
+//! use extrahop::users;
+//!
+//! let report = users::offboard(&client, "kenp", "setup").await?;
+//! for (dashboard, error) in &report.failed {
+//!     eprintln!("Unable to transfer '{}': {}", dashboard.name, error);
+//! }
+//! ```
+
+use crate::api_keys::ApiKey;
+use crate::dashboards::{self, Dashboard};
+use crate::{path, ApiResponse, Client, Error};
+use secstr::SecUtf8;
+use serde::{Deserialize, Serialize};
+
+/// A user account as returned by the REST API.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct User {
+    pub username: String,
+    /// The user's display name.
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub user_type: UserType,
+    #[serde(default)]
+    pub enabled: bool,
+    /// The privileges assigned directly to the user.
+    #[serde(default)]
+    pub granted_roles: Privileges,
+    /// The privileges the user has after including those granted by remote groups.
+    #[serde(default)]
+    pub effective_roles: Privileges,
+    /// The time the user last logged in, in milliseconds since epoch.
+    #[serde(default)]
+    pub last_login_time: Option<u64>,
+}
+
+/// Where a user account is authenticated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum UserType {
+    /// The account and its password are stored on the appliance.
+    Local,
+    /// The account is authenticated by LDAP, RADIUS, TACACS+ or SAML.
+    Remote,
+    #[serde(other, serialize_with = "crate::unknown::reject")]
+    Other,
+}
+
+/// The privileges granted to a user. Unset privileges are not granted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Privileges {
+    /// Access to system and access administration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<ModuleAccess>,
+    /// Access to create and modify customizations, such as dashboards and alerts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub write: Option<WriteAccess>,
+    /// Access to network detection and response features.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ndr: Option<ModuleAccess>,
+    /// Access to network performance monitoring features.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub npm: Option<ModuleAccess>,
+    /// Access to packets and session keys.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub packets: Option<PacketAccess>,
+}
+
+impl Privileges {
+    /// Privileges for a user who can view, but not change, anything on the appliance.
+    pub fn read_only() -> Self {
+        Self {
+            ndr: Some(ModuleAccess::Full),
+            npm: Some(ModuleAccess::Full),
+            ..Default::default()
+        }
+    }
+
+    /// Privileges for a user who can do everything on the appliance.
+    pub fn system_admin() -> Self {
+        Self {
+            system: Some(ModuleAccess::Full),
+            write: Some(WriteAccess::Full),
+            ndr: Some(ModuleAccess::Full),
+            npm: Some(ModuleAccess::Full),
+            packets: Some(PacketAccess::FullWithKeys),
+        }
+    }
+}
+
+/// Whether a user can access a module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ModuleAccess {
+    Full,
+    None,
+    #[serde(other, serialize_with = "crate::unknown::reject")]
+    Other,
+}
+
+/// What a user can create and modify.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum WriteAccess {
+    /// Create and modify all customizations.
+    Full,
+    /// Create and modify dashboards and alerts, and share dashboards.
+    Limited,
+    /// Create and modify their own dashboards, without sharing them.
+    Personal,
+    #[serde(other, serialize_with = "crate::unknown::reject")]
+    Other,
+}
+
+/// How much of the captured traffic a user can download.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum PacketAccess {
+    /// Packets, including session keys for decryption.
+    FullWithKeys,
+    /// Packets without session keys.
+    Full,
+    /// Only the first 64 bytes of each packet.
+    Slices,
+    #[serde(other, serialize_with = "crate::unknown::reject")]
+    Other,
+}
+
+/// The properties of a user account to be created.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NewUser {
+    pub username: String,
+    pub name: String,
+    /// The password for a local user. Remote users are authenticated elsewhere and do not
+    /// have a password.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<SecUtf8>,
+    #[serde(rename = "type")]
+    pub user_type: UserType,
+    pub enabled: bool,
+    pub granted_roles: Privileges,
+}
+
+impl NewUser {
+    /// Create an enabled local user with the specified password.
+    pub fn local(
+        username: impl Into<String>,
+        name: impl Into<String>,
+        password: SecUtf8,
+        granted_roles: Privileges,
+    ) -> Self {
+        Self {
+            username: username.into(),
+            name: name.into(),
+            password: Some(password),
+            user_type: UserType::Local,
+            enabled: true,
+            granted_roles,
+        }
+    }
+
+    /// Create an enabled remote user, who will log in using an external identity provider.
+    pub fn remote(
+        username: impl Into<String>,
+        name: impl Into<String>,
+        granted_roles: Privileges,
+    ) -> Self {
+        Self {
+            username: username.into(),
+            name: name.into(),
+            password: None,
+            user_type: UserType::Remote,
+            enabled: true,
+            granted_roles,
+        }
+    }
+}
+
+/// Changes to apply to an existing user. Fields left as `None` are not changed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UserUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<SecUtf8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Replacement privileges. When set, privileges not included are revoked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub granted_roles: Option<Privileges>,
+}
+
+/// The outcome of [`offboard`].
+#[derive(Debug, Default)]
+pub struct OffboardReport {
+    /// The dashboards which now belong to the successor.
+    pub transferred: Vec<Dashboard>,
+    /// The dashboards which could not be transferred, along with the reason.
+    pub failed: Vec<(Dashboard, Error)>,
+    /// Whether the user was deleted. This is `false` if any dashboard could not be
+    /// transferred.
+    pub deleted: bool,
+}
+
+impl OffboardReport {
+    /// Returns `true` if every dashboard was transferred and the user was deleted.
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty() && self.deleted
+    }
+}
+
+/// Get all users on the appliance.
+pub async fn list(client: &Client) -> Result<Vec<User>, Error> {
+    client
+        .get("v1/users")?
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Get a single user by username.
+pub async fn get(client: &Client, username: &str) -> Result<User, Error> {
+    client
+        .get(&format!("v1/users/{}", path::segment(username)))?
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Create a new user.
+pub async fn create(client: &Client, user: &NewUser) -> Result<(), Error> {
+    client
+        .post("v1/users")?
+        .json(user)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+/// Apply changes to an existing user.
+pub async fn update(client: &Client, username: &str, update: &UserUpdate) -> Result<(), Error> {
+    client
+        .patch(&format!("v1/users/{}", path::segment(username)))?
+        .json(update)
+        .send()
+        .await?
+        .validate_status()
+        .await?;
+
+    Ok(())
+}
+
+/// Replace the privileges granted to a user.
+pub async fn set_privileges(
+    client: &Client,
+    username: &str,
+    privileges: &Privileges,
+) -> Result<(), Error> {
+    update(
+        client,
+        username,
+        &UserUpdate {
+            granted_roles: Some(privileges.clone()),
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+/// Delete a user.
+///
+/// If `transfer_to` is set, the user's customizations are given to that user rather than
+/// deleted. To transfer dashboards and confirm each transfer before deleting, see
+/// [`offboard`].
+pub async fn delete(
+    client: &Client,
+    username: &str,
+    transfer_to: Option<&str>,
+) -> Result<(), Error> {
+    let mut request = client.delete(&format!("v1/users/{}", path::segment(username)))?;
+    if let Some(dest_user) = transfer_to {
+        request = request.query(&[("dest_user", dest_user)]);
+    }
+
+    request.send().await?.validate_status().await?;
+
+    Ok(())
+}
+
+/// Get the API keys belonging to a user.
+pub async fn api_keys(client: &Client, username: &str) -> Result<Vec<ApiKey>, Error> {
+    client
+        .get(&format!("v1/users/{}/apikeys", path::segment(username)))?
+        .send()
+        .await?
+        .validate_and_read()
+        .await
+}
+
+/// Transfer a user's dashboards and customizations to `successor`, then delete the user.
+///
+/// An error is returned only if the dashboards could not be listed or the deletion failed.
+/// If any dashboard could not be transferred, the user is not deleted so that no work is
+/// lost; the failures are listed in the report and the call can be retried.
+pub async fn offboard(
+    client: &Client,
+    username: &str,
+    successor: &str,
+) -> Result<OffboardReport, Error> {
+    let mut report = OffboardReport::default();
+    for (dashboard, result) in dashboards::transfer_ownership(client, username, successor).await? {
+        match result {
+            Ok(()) => report.transferred.push(dashboard),
+            Err(e) => report.failed.push((dashboard, e)),
+        }
+    }
+
+    if report.failed.is_empty() {
+        delete(client, username, Some(successor)).await?;
+        report.deleted = true;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ModuleAccess, NewUser, PacketAccess, Privileges, User, UserType, WriteAccess};
+    use serde_json::json;
+
+    #[test]
+    fn serialize_new_user() {
+        let user = NewUser::remote("jdoe", "Jane Doe", Privileges::read_only());
+        assert_eq!(
+            json!({
+                "username": "jdoe",
+                "name": "Jane Doe",
+                "type": "remote",
+                "enabled": true,
+                "granted_roles": { "ndr": "full", "npm": "full" }
+            }),
+            serde_json::to_value(user).unwrap()
+        );
+    }
+
+    #[test]
+    fn deserialize_user() {
+        let user: User = serde_json::from_value(json!({
+            "username": "setup",
+            "name": "Setup",
+            "type": "local",
+            "enabled": true,
+            "granted_roles": { "system": "full", "write": "limited", "packets": "slices" },
+            "effective_roles": { "system": "full", "write": "limited", "packets": "future" }
+        }))
+        .unwrap();
+
+        assert_eq!(UserType::Local, user.user_type);
+        assert_eq!(Some(ModuleAccess::Full), user.granted_roles.system);
+        assert_eq!(Some(WriteAccess::Limited), user.granted_roles.write);
+        assert_eq!(Some(PacketAccess::Slices), user.granted_roles.packets);
+        assert_eq!(Some(PacketAccess::Other), user.effective_roles.packets);
+        assert_eq!(None, user.last_login_time);
+    }
+
+    #[test]
+    fn unknown_user_type_not_sent() {
+        let user = NewUser {
+            user_type: UserType::Other,
+            ..NewUser::remote("jdoe", "Jane Doe", Privileges::read_only())
+        };
+        assert!(serde_json::to_value(user).is_err());
+    }
+}